@@ -0,0 +1,110 @@
+//! Compares canonicalizing JSON text directly with the round trip through [serde_json::Value].
+//!
+//! Run with `cargo bench --bench canonicalize`.
+
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use serde_canonical_json::{CanonicalFormatter, canonicalize_slice, direct};
+
+
+/// A small deterministic generator, so every run sees the same corpus.
+struct Lcg(u64);
+
+
+impl Lcg
+{
+    fn next(&mut self, below: u64) -> u64
+    {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % below
+    }
+}
+
+
+/// Documents shaped like API responses and manifests, with members in no particular order.
+///
+/// No object has a duplicate key, which [direct] keeps but a [serde_json::Value] does not.
+fn corpus() -> Vec<Vec<u8>>
+{
+    let mut rng = Lcg(0x5eed);
+    let words = ["name", "version", "id", "digest", "created_at", "labels", "uri", "size", "kind", "owner", "tags", "é", "line\\nbreak", "quote\\\""];
+
+    (0..200).map(|_|
+    {
+        let items: Vec<_> = (0..(20 + rng.next(60))).map(|index|
+        {
+            let mut members: Vec<_> = words.iter().take(6 + rng.next(8) as usize).map(|key| match rng.next(4)
+            {
+                0 => format!(r#""{}": {}"#, key, rng.next(1 << 40)),
+                1 => format!(r#""{}": "{}-{}""#, key, words[rng.next(words.len() as u64) as usize], rng.next(1000)),
+                2 => format!(r#""{}": [true, null, {}, "x"]"#, key, index),
+                _ => format!(r#""{}": {{ "sha256": "{:016x}{:016x}", "size": {} }}"#, key, rng.next(u64::MAX), rng.next(u64::MAX), rng.next(1 << 20)),
+            })
+            .collect();
+
+            members.reverse();
+            format!("{{ {} }}", members.join(", "))
+        })
+        .collect();
+
+        format!(r#"{{ "items": [ {} ], "count": {}, "next": null }}"#, items.join(",\n  "), items.len()).into_bytes()
+    })
+    .collect()
+}
+
+
+fn value_round_trip(json: &[u8]) -> Vec<u8>
+{
+    let value: serde_json::Value = serde_json::from_slice(json).unwrap();
+
+    let mut ser = serde_json::Serializer::with_formatter(Vec::new(), CanonicalFormatter::new());
+    value.serialize(&mut ser).unwrap();
+
+    ser.into_inner()
+}
+
+
+fn measure(corpus: &[Vec<u8>], canonicalize: impl Fn(&[u8]) -> Vec<u8>) -> Duration
+{
+    (0..5).map(|_|
+    {
+        let start = Instant::now();
+
+        for json in corpus
+        {
+            std::hint::black_box(canonicalize(std::hint::black_box(json)));
+        }
+
+        start.elapsed()
+    })
+    .min()
+    .unwrap_or_default()
+}
+
+
+fn main()
+{
+    let corpus = corpus();
+    let bytes: usize = corpus.iter().map(Vec::len).sum();
+
+    for json in &corpus
+    {
+        assert_eq!(direct::canonicalize(json).unwrap(), value_round_trip(json));
+    }
+
+    let baseline = measure(&corpus, value_round_trip);
+
+    println!("corpus: {} documents, {:.1} MiB", corpus.len(), bytes as f64 / (1024.0 * 1024.0));
+
+    for (name, time) in
+    [
+        ("serde_json::Value round trip", baseline),
+        ("canonicalize_slice", measure(&corpus, |json| canonicalize_slice(json, CanonicalFormatter::new()).unwrap())),
+        ("direct::canonicalize", measure(&corpus, |json| direct::canonicalize(json).unwrap())),
+    ]
+    {
+        let throughput = bytes as f64 / time.as_secs_f64() / (1024.0 * 1024.0);
+
+        println!("{:<30} {:>9.2?} {:>8.1} MiB/s {:>6.2}x", name, time, throughput, baseline.as_secs_f64() / time.as_secs_f64());
+    }
+}
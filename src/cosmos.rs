@@ -0,0 +1,252 @@
+//! # Cosmos SDK Amino sign docs
+//!
+//! Builds the legacy Amino JSON `StdSignDoc` that Cosmos SDK chains sign with `SIGN_MODE_LEGACY_AMINO_JSON`,
+//! and signs and verifies it with secp256k1 ECDSA.
+//!
+//! The sign bytes are serialized with [Profile::Amino]: keys sorted, `<`, `>` and `&` escaped like Go,
+//! and 64-bit integers such as `account_number`, `sequence` and `gas` written as strings.
+//!
+//! ```rust
+//! use k256::ecdsa::SigningKey;
+//! use serde_json::json;
+//! use serde_canonical_json::cosmos::{self, Coin, Msg, StdFee, StdSignDoc};
+//!
+//! let key = SigningKey::from_slice(&[1; 32]).unwrap();
+//!
+//! let doc = StdSignDoc
+//! {
+//!     account_number: 7,
+//!     chain_id: "cosmoshub-4".to_owned(),
+//!     fee: StdFee::new(vec![Coin::new("uatom", "5000")], 200000),
+//!     memo: String::new(),
+//!     msgs: vec![Msg::new("cosmos-sdk/MsgSend", json!({ "amount": [{ "amount": "1", "denom": "uatom" }] }))],
+//!     sequence: 0,
+//!     timeout_height: 0,
+//! };
+//!
+//! let bytes = cosmos::sign_bytes(&doc).unwrap();
+//! assert!(String::from_utf8(bytes).unwrap().starts_with(r#"{"account_number":"7","chain_id":"cosmoshub-4""#));
+//!
+//! let signature = cosmos::sign(&doc, &key).unwrap();
+//! cosmos::verify(&doc, &signature).unwrap();
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use k256::ecdsa::{Signature, SigningKey, VerifyingKey, signature::{Signer, Verifier}};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde_json::Value;
+use crate::{CanonicalFormatter, Profile, to_vec_with_formatter};
+
+
+/// The Amino type of a compressed secp256k1 public key.
+pub const PUB_KEY_SECP256K1: &str = "tendermint/PubKeySecp256k1";
+
+
+/// The document signed by a legacy Amino transaction.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StdSignDoc
+{
+    #[serde(deserialize_with = "u64_from_string")]
+    pub account_number: u64,
+    pub chain_id: String,
+    pub fee: StdFee,
+    pub memo: String,
+    pub msgs: Vec<Msg>,
+    #[serde(deserialize_with = "u64_from_string")]
+    pub sequence: u64,
+    /// Left out of the sign doc when zero, as the SDK's `omitempty` does.
+    #[serde(default, skip_serializing_if = "is_zero", deserialize_with = "u64_from_string")]
+    pub timeout_height: u64,
+}
+
+
+/// The fee of a transaction.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StdFee
+{
+    pub amount: Vec<Coin>,
+    #[serde(deserialize_with = "u64_from_string")]
+    pub gas: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub granter: Option<String>,
+}
+
+
+impl StdFee
+{
+    pub fn new(amount: Vec<Coin>, gas: u64) -> Self
+    {
+        Self { amount, gas, payer: None, granter: None }
+    }
+}
+
+
+/// An amount of a single denomination.
+///
+/// The amount is an arbitrary precision integer on chain, so it is kept as its decimal string.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Coin
+{
+    pub amount: String,
+    pub denom: String,
+}
+
+
+impl Coin
+{
+    pub fn new(denom: &str, amount: &str) -> Self
+    {
+        Self { amount: amount.to_owned(), denom: denom.to_owned() }
+    }
+}
+
+
+/// A message in its Amino JSON form, such as `{"type":"cosmos-sdk/MsgSend","value":{...}}`.
+///
+/// The value has no Go types to decide which integers Amino quotes, so it must already be in Amino JSON
+/// form: its integers are written as they are, and `int64` and `uint64` fields must be strings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Msg
+{
+    #[serde(rename = "type")]
+    pub type_url: String,
+    #[serde(serialize_with = "as_written")]
+    pub value: Value,
+}
+
+
+impl Msg
+{
+    pub fn new(type_url: &str, value: Value) -> Self
+    {
+        Self { type_url: type_url.to_owned(), value }
+    }
+}
+
+
+/// An Amino JSON public key.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PubKey
+{
+    #[serde(rename = "type")]
+    pub type_url: String,
+    pub value: String,
+}
+
+
+/// A signature over a [StdSignDoc], along with the key that made it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StdSignature
+{
+    pub pub_key: PubKey,
+    pub signature: String,
+}
+
+
+/// Serializes `doc` into the bytes that are signed.
+pub fn sign_bytes(doc: &StdSignDoc) -> io::Result<Vec<u8>>
+{
+    to_vec_with_formatter(doc, CanonicalFormatter::with_profile(Profile::Amino))
+}
+
+
+/// Signs `doc` with a low-S secp256k1 ECDSA signature over the SHA-256 of its sign bytes.
+pub fn sign(doc: &StdSignDoc, key: &SigningKey) -> io::Result<StdSignature>
+{
+    let signature: Signature = key.sign(&sign_bytes(doc)?);
+
+    Ok(StdSignature
+    {
+        pub_key: public_key(key.verifying_key()),
+        signature: STANDARD.encode(signature.to_bytes()),
+    })
+}
+
+
+/// Checks that `signature` is a valid signature of `doc` by the key it carries.
+///
+/// High-S signatures are rejected, as they are on chain.
+pub fn verify(doc: &StdSignDoc, signature: &StdSignature) -> io::Result<()>
+{
+    if signature.pub_key.type_url != PUB_KEY_SECP256K1
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Public key must be a secp256k1 key."))
+    }
+
+    let Ok(key) = decode(&signature.pub_key.value).and_then(|bytes| VerifyingKey::from_sec1_bytes(&bytes).map_err(|_| ())) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Public key is not a valid secp256k1 key."))
+    };
+
+    let Ok(value) = decode(&signature.signature).and_then(|bytes| Signature::from_slice(&bytes).map_err(|_| ())) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature is not a 64 byte secp256k1 signature."))
+    };
+
+    if key.verify(&sign_bytes(doc)?, &value).is_err()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature is invalid."))
+    }
+
+    Ok(())
+}
+
+
+/// The Amino JSON form of a public key, with the key compressed.
+pub fn public_key(key: &VerifyingKey) -> PubKey
+{
+    PubKey
+    {
+        type_url: PUB_KEY_SECP256K1.to_owned(),
+        value: STANDARD.encode(key.to_encoded_point(true).as_bytes()),
+    }
+}
+
+
+fn decode(encoded: &str) -> Result<Vec<u8>, ()>
+{
+    STANDARD.decode(encoded).map_err(|_| ())
+}
+
+
+fn is_zero(value: &u64) -> bool
+{
+    *value == 0
+}
+
+
+/// Writes an untyped message value with its integers as they are.
+fn as_written<S>(value: &Value, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+{
+    crate::hint::untyped(|| value.serialize(serializer))
+}
+
+
+/// Amino writes 64-bit integers as strings, but plain numbers are accepted too.
+fn u64_from_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
+    where
+        D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Wide
+    {
+        String(String),
+        Number(u64),
+    }
+
+    match Wide::deserialize(deserializer)?
+    {
+        Wide::String(string) => string.parse().map_err(serde::de::Error::custom),
+        Wide::Number(number) => Ok(number),
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
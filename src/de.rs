@@ -0,0 +1,656 @@
+//! A deserializer that only accepts JSON in the canonical form [CanonicalFormatter::new] writes.
+//!
+//! [CanonicalFormatter::new]: crate::CanonicalFormatter::new
+
+use std::{borrow::Cow, fmt, io};
+use serde::de::{self, DeserializeSeed, Deserialize, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use crate::{CanonicalError, order::OlpcEscaped, KeyOrder};
+
+
+/// Deserializes `bytes`, which must be canonical JSON.
+///
+/// The input is checked while it is read, so a document is only accepted in the one form
+/// [CanonicalFormatter::new](crate::CanonicalFormatter::new) writes it in: no whitespace, unique
+/// keys in order, only `"` and `\` escaped, and integers without leading zeros or a negative zero.
+/// Errors carry the byte offset they were found at, as [CanonicalError::NotCanonical] when the input
+/// is JSON in another form, [CanonicalError::InvalidJson] when it is not JSON, and
+/// [CanonicalError::Deserialize] when `T` rejects it.
+pub fn from_canonical_slice<'de, T>(bytes: &'de [u8]) -> io::Result<T>
+    where
+        T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer { input: bytes, position: 0, remaining_depth: 128 };
+
+    let value = T::deserialize(&mut deserializer).map_err(|error| error.at(deserializer.position))?;
+
+    if deserializer.position < bytes.len()
+    {
+        return Err(deserializer.unexpected().into())
+    }
+
+    Ok(value)
+}
+
+
+/// Deserializes `json`, which must be canonical JSON, as [from_canonical_slice] does.
+pub fn from_canonical_str<'de, T>(json: &'de str) -> io::Result<T>
+    where
+        T: Deserialize<'de>,
+{
+    from_canonical_slice(json.as_bytes())
+}
+
+
+/// The error of the deserializer, where messages from `T` still need their offset.
+#[derive(Debug)]
+enum Error
+{
+    Canonical(CanonicalError),
+    Custom(String),
+}
+
+
+impl Error
+{
+    fn at(self, offset: usize) -> CanonicalError
+    {
+        match self
+        {
+            Self::Canonical(error) => error,
+            Self::Custom(message) => CanonicalError::Deserialize { offset, message },
+        }
+    }
+}
+
+
+impl fmt::Display for Error
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::Canonical(error) => error.fmt(f),
+            Self::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+
+impl std::error::Error for Error {}
+
+
+impl de::Error for Error
+{
+    fn custom<T: fmt::Display>(message: T) -> Self
+    {
+        Self::Custom(message.to_string())
+    }
+}
+
+
+struct Deserializer<'de>
+{
+    input: &'de [u8],
+    position: usize,
+    remaining_depth: usize,
+}
+
+
+impl<'de> Deserializer<'de>
+{
+    fn peek(&self) -> Option<u8>
+    {
+        self.input.get(self.position).copied()
+    }
+
+
+    fn invalid(&self, offset: usize, reason: &'static str) -> Error
+    {
+        Error::Canonical(CanonicalError::InvalidJson { offset, reason })
+    }
+
+
+    fn not_canonical(&self, offset: usize, reason: &'static str) -> Error
+    {
+        Error::Canonical(CanonicalError::NotCanonical { offset, reason })
+    }
+
+
+    /// The error for a byte that cannot come next.
+    fn unexpected(&self) -> CanonicalError
+    {
+        let offset = self.position;
+
+        match self.peek()
+        {
+            Some(b' ' | b'\t' | b'\n' | b'\r') => CanonicalError::NotCanonical { offset, reason: "Whitespace is not allowed" },
+            Some(_) => CanonicalError::InvalidJson { offset, reason: "Unexpected character" },
+            None => CanonicalError::InvalidJson { offset, reason: "Unexpected end of input" },
+        }
+    }
+
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error>
+    {
+        if self.peek() != Some(byte)
+        {
+            return Err(Error::Canonical(self.unexpected()))
+        }
+
+        self.position += 1;
+
+        Ok(())
+    }
+
+
+    fn literal(&mut self, literal: &'static [u8]) -> Result<(), Error>
+    {
+        if !self.input[self.position..].starts_with(literal)
+        {
+            return Err(self.invalid(self.position, "Invalid literal"))
+        }
+
+        self.position += literal.len();
+
+        Ok(())
+    }
+
+
+    fn nest(&mut self) -> Result<(), Error>
+    {
+        self.remaining_depth = self.remaining_depth.checked_sub(1).ok_or(Error::Canonical(CanonicalError::TooDeep(128)))?;
+
+        Ok(())
+    }
+
+
+    /// Reads a string after its opening quote, borrowing it from the input unless it has escapes.
+    fn string(&mut self) -> Result<Cow<'de, str>, Error>
+    {
+        let start = self.position;
+        let mut owned: Option<Vec<u8>> = None;
+        let mut run = start;
+
+        loop
+        {
+            let Some(byte) = self.peek() else
+            {
+                return Err(self.invalid(self.position, "Unterminated string"))
+            };
+
+            match byte
+            {
+                b'"' =>
+                {
+                    let raw = match std::str::from_utf8(&self.input[start..self.position])
+                    {
+                        Ok(raw) => raw,
+                        Err(error) => return Err(self.invalid(start + error.valid_up_to(), "String is not valid UTF-8")),
+                    };
+
+                    let string = match owned
+                    {
+                        // Unescaping only removed ASCII backslashes, so the rest is still UTF-8
+                        Some(mut owned) =>
+                        {
+                            owned.extend_from_slice(&self.input[run..self.position]);
+                            Cow::Owned(String::from_utf8(owned).unwrap_or_default())
+                        }
+                        None => Cow::Borrowed(raw),
+                    };
+
+                    self.position += 1;
+
+                    return Ok(string)
+                }
+                b'\\' =>
+                {
+                    // OLPC only escapes the quote and the backslash
+                    match self.input.get(self.position + 1)
+                    {
+                        Some(escaped @ (b'"' | b'\\')) =>
+                        {
+                            let owned = owned.get_or_insert_with(Vec::new);
+                            owned.extend_from_slice(&self.input[run..self.position]);
+                            owned.push(*escaped);
+
+                            self.position += 2;
+                            run = self.position;
+                        }
+                        Some(b'/' | b'b' | b'f' | b'n' | b'r' | b't' | b'u') => return Err(self.not_canonical(self.position, "Only '\"' and '\\' may be escaped")),
+                        _ => return Err(self.invalid(self.position, "Invalid escape")),
+                    }
+                }
+                _ => self.position += 1,
+            }
+        }
+    }
+
+
+    fn number<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        let start = self.position;
+        let negative = self.peek() == Some(b'-');
+
+        if negative
+        {
+            self.position += 1;
+        }
+
+        let digits = self.position;
+
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit())
+        {
+            self.position += 1;
+        }
+
+        match &self.input[digits..self.position]
+        {
+            [] => return Err(self.invalid(self.position, "Invalid number")),
+            [b'0', _, ..] => return Err(self.invalid(digits, "Number has leading zeros")),
+            [b'0'] if negative => return Err(self.not_canonical(start, "Negative zero is not canonical")),
+            _ => (),
+        }
+
+        if let Some(b'.' | b'e' | b'E') = self.peek()
+        {
+            return Err(self.not_canonical(start, "Floating point numbers are forbidden"))
+        }
+
+        // Only ASCII was read
+        let text = std::str::from_utf8(&self.input[start..self.position]).unwrap_or_default();
+
+        if negative
+        {
+            match (text.parse(), text.parse())
+            {
+                (Ok(value), _) => visitor.visit_i64(value),
+                (_, Ok(value)) => visitor.visit_i128(value),
+                _ => Err(self.invalid(start, "Integer is out of range")),
+            }
+        }
+        else
+        {
+            match (text.parse(), text.parse())
+            {
+                (Ok(value), _) => visitor.visit_u64(value),
+                (_, Ok(value)) => visitor.visit_u128(value),
+                _ => Err(self.invalid(start, "Integer is out of range")),
+            }
+        }
+    }
+}
+
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de>
+{
+    type Error = Error;
+
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        match self.peek()
+        {
+            Some(b'n') =>
+            {
+                self.literal(b"null")?;
+                visitor.visit_unit()
+            }
+            Some(b't') =>
+            {
+                self.literal(b"true")?;
+                visitor.visit_bool(true)
+            }
+            Some(b'f') =>
+            {
+                self.literal(b"false")?;
+                visitor.visit_bool(false)
+            }
+            Some(b'"') =>
+            {
+                self.position += 1;
+
+                match self.string()?
+                {
+                    Cow::Borrowed(string) => visitor.visit_borrowed_str(string),
+                    Cow::Owned(string) => visitor.visit_string(string),
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(visitor),
+            Some(b'[') =>
+            {
+                self.position += 1;
+                self.nest()?;
+
+                let value = visitor.visit_seq(Sequence { de: &mut *self, first: true })?;
+
+                self.remaining_depth += 1;
+                self.expect(b']')?;
+
+                Ok(value)
+            }
+            Some(b'{') =>
+            {
+                self.position += 1;
+                self.nest()?;
+
+                let value = visitor.visit_map(Members { de: &mut *self, previous: None })?;
+
+                self.remaining_depth += 1;
+                self.expect(b'}')?;
+
+                Ok(value)
+            }
+            _ => Err(Error::Canonical(self.unexpected())),
+        }
+    }
+
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        if self.peek() == Some(b'n')
+        {
+            self.literal(b"null")?;
+            visitor.visit_none()
+        }
+        else
+        {
+            visitor.visit_some(self)
+        }
+    }
+
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        match self.peek()
+        {
+            Some(b'"') =>
+            {
+                self.position += 1;
+                let variant = self.string()?;
+
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            Some(b'{') =>
+            {
+                self.position += 1;
+                self.nest()?;
+                self.expect(b'"')?;
+
+                let value = visitor.visit_enum(Variant { de: &mut *self })?;
+
+                self.remaining_depth += 1;
+                self.expect(b'}')?;
+
+                Ok(value)
+            }
+            _ => Err(Error::Canonical(self.unexpected())),
+        }
+    }
+
+
+    serde::forward_to_deserialize_any!
+    {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+
+struct Sequence<'a, 'de>
+{
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+
+impl<'de> SeqAccess<'de> for Sequence<'_, 'de>
+{
+    type Error = Error;
+
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+        where
+            T: DeserializeSeed<'de>,
+    {
+        if self.de.peek() == Some(b']')
+        {
+            return Ok(None)
+        }
+
+        if !self.first
+        {
+            self.de.expect(b',')?;
+        }
+
+        self.first = false;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+
+struct Members<'a, 'de>
+{
+    de: &'a mut Deserializer<'de>,
+    previous: Option<Cow<'de, str>>,
+}
+
+
+impl<'de> MapAccess<'de> for Members<'_, 'de>
+{
+    type Error = Error;
+
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+        where
+            K: DeserializeSeed<'de>,
+    {
+        if self.de.peek() == Some(b'}')
+        {
+            return Ok(None)
+        }
+
+        if self.previous.is_some()
+        {
+            self.de.expect(b',')?;
+        }
+
+        let start = self.de.position;
+
+        self.de.expect(b'"')?;
+        let key = self.de.string()?;
+
+        match &self.previous
+        {
+            Some(previous) if *previous == key => return Err(self.de.not_canonical(start, "Object has duplicate keys")),
+            Some(previous) if OlpcEscaped.compare(previous, &key).is_gt() => return Err(self.de.not_canonical(start, "Keys are not in order")),
+            _ => (),
+        }
+
+        self.previous = Some(key.clone());
+        self.de.expect(b':')?;
+
+        seed.deserialize(MapKey(key, start)).map(Some)
+    }
+
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+        where
+            V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+
+struct Variant<'a, 'de>
+{
+    de: &'a mut Deserializer<'de>,
+}
+
+
+impl<'de> de::EnumAccess<'de> for Variant<'_, 'de>
+{
+    type Error = Error;
+    type Variant = Self;
+
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Error>
+        where
+            V: DeserializeSeed<'de>,
+    {
+        // Just past the opening quote
+        let start = self.de.position - 1;
+        let variant = self.de.string()?;
+        let value = seed.deserialize(MapKey(variant, start))?;
+
+        self.de.expect(b':')?;
+
+        Ok((value, self))
+    }
+}
+
+
+impl<'de> de::VariantAccess<'de> for Variant<'_, 'de>
+{
+    type Error = Error;
+
+
+    fn unit_variant(self) -> Result<(), Error>
+    {
+        Deserialize::deserialize(self.de)
+    }
+
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+        where
+            T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+
+/// An object key and its offset, which can also stand for an integer as it does in a map with integer keys.
+struct MapKey<'de>(Cow<'de, str>, usize);
+
+
+macro_rules! deserialize_integer_key
+{
+    ($($method:ident => $visit:ident($integer:ty),)*) =>
+    {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+                where
+                    V: Visitor<'de>,
+            {
+                let MapKey(key, offset) = self;
+
+                match key.parse::<$integer>()
+                {
+                    // Leading zeros, a plus sign or negative zero would give several keys for one integer
+                    Ok(value) if value.to_string() == key => visitor.$visit(value),
+                    Ok(_) => Err(Error::Canonical(CanonicalError::NotCanonical { offset, reason: "Integer key is not in canonical form" })),
+                    Err(_) =>
+                    {
+                        let error: Error = de::Error::invalid_value(de::Unexpected::Str(&key), &visitor);
+                        Err(Error::Canonical(error.at(offset)))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+
+impl<'de> de::Deserializer<'de> for MapKey<'de>
+{
+    type Error = Error;
+
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        match self.0
+        {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+
+    deserialize_integer_key!
+    {
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+    }
+
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error>
+        where
+            V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+
+    serde::forward_to_deserialize_any!
+    {
+        bool f32 f64 char str string bytes byte_buf option unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,158 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::{CanonicalError, CanonicalFormatter, from_canonical_slice, from_canonical_str};
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Kind
+{
+    Plain,
+    Tagged(u8),
+    Pair(i32, i32),
+    Named { value: String },
+}
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Document<'a>
+{
+    name: &'a str,
+    escaped: String,
+    count: i128,
+    big: u128,
+    missing: Option<bool>,
+    kinds: Vec<Kind>,
+    by_id: BTreeMap<u32, String>,
+    nested: HashMap<String, Vec<()>>,
+}
+
+
+fn error<T: std::fmt::Debug>(result: std::io::Result<T>) -> CanonicalError
+{
+    CanonicalError::from_io(&result.unwrap_err()).cloned().unwrap()
+}
+
+
+#[test]
+fn round_trip()
+{
+    let document = Document
+    {
+        name: "plain",
+        escaped: "quote \" backslash \\ newline \n".to_owned(),
+        count: -170141183460469231731687303715884105728,
+        big: u128::MAX,
+        missing: None,
+        kinds: vec![Kind::Plain, Kind::Tagged(7), Kind::Pair(-1, 2), Kind::Named { value: "v".to_owned() }],
+        by_id: BTreeMap::from([(2, "two".to_owned()), (10, "ten".to_owned())]),
+        nested: HashMap::from([("a".to_owned(), vec![(), ()]), ("b".to_owned(), vec![])]),
+    };
+
+    let mut ser = serde_json::Serializer::with_formatter(Vec::new(), CanonicalFormatter::new());
+    document.serialize(&mut ser).unwrap();
+
+    let bytes = ser.into_inner();
+    let parsed: Document = from_canonical_slice(&bytes).unwrap();
+
+    assert_eq!(parsed, document);
+
+    let value: serde_json::Value = from_canonical_str(r#"{"a":[1,-2,true,null,{}],"b":"\"x\""}"#).unwrap();
+    assert_eq!(value, serde_json::json!({ "a": [1, -2, true, null, {}], "b": "\"x\"" }));
+}
+
+
+#[test]
+fn rejects_other_forms()
+{
+    let not_canonical = |json: &str|
+    {
+        match error(from_canonical_str::<serde_json::Value>(json))
+        {
+            CanonicalError::NotCanonical { offset, .. } => offset,
+            other => panic!("{} failed with {:?}", json, other),
+        }
+    };
+
+    assert_eq!(not_canonical(r#"{"a": 1}"#), 5);
+    assert_eq!(not_canonical(r#"[1, 2]"#), 3);
+    assert_eq!(not_canonical(" 1"), 0);
+    assert_eq!(not_canonical("[1]\n"), 3);
+    assert_eq!(not_canonical(r#"{"b":1,"a":2}"#), 7);
+    assert_eq!(not_canonical(r#"{"a":1,"a":2}"#), 7);
+    assert_eq!(not_canonical(r#"["a\nb"]"#), 3);
+    assert_eq!(not_canonical(r#"["\u0041"]"#), 2);
+    assert_eq!(not_canonical(r#"["a\/b"]"#), 3);
+    assert_eq!(not_canonical("[1.5]"), 1);
+    assert_eq!(not_canonical("[1e3]"), 1);
+    assert_eq!(not_canonical("[-0]"), 1);
+
+    // Keys are in OLPC order, where the closing quote sorts after '!'
+    assert!(from_canonical_str::<serde_json::Value>(r#"{"a!":1,"a":2}"#).is_ok());
+    assert_eq!(not_canonical(r#"{"a":2,"a!":1}"#), 7);
+}
+
+
+#[test]
+fn rejects_invalid_json()
+{
+    let invalid = |json: &[u8]|
+    {
+        match error(from_canonical_slice::<serde_json::Value>(json))
+        {
+            CanonicalError::InvalidJson { offset, .. } => offset,
+            other => panic!("{:?} failed with {:?}", json, other),
+        }
+    };
+
+    assert_eq!(invalid(b""), 0);
+    assert_eq!(invalid(b"[01]"), 1);
+    assert_eq!(invalid(b"[1,]"), 3);
+    assert_eq!(invalid(b"[1]]"), 3);
+    assert_eq!(invalid(b"{\"a\"}"), 4);
+    assert_eq!(invalid(b"[\"a\xFFb\"]"), 3);
+    assert_eq!(invalid(b"[\"a"), 3);
+    assert_eq!(invalid(b"[nul]"), 1);
+    assert_eq!(invalid(b"[\"\\x\"]"), 2);
+
+    assert_eq!(error(from_canonical_str::<serde_json::Value>(&"[".repeat(200))), CanonicalError::TooDeep(128));
+}
+
+
+#[test]
+fn type_errors_have_offsets()
+{
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Typed
+    {
+        a: u8,
+        b: String,
+    }
+
+    let CanonicalError::Deserialize { offset, .. } = error(from_canonical_str::<Typed>(r#"{"a":300,"b":"x"}"#)) else { panic!() };
+    assert_eq!(offset, 8);
+
+    let CanonicalError::Deserialize { offset, message } = error(from_canonical_str::<Typed>(r#"{"a":1}"#)) else { panic!() };
+    assert_eq!((offset, message.as_str()), (6, "missing field `b`"));
+}
+
+
+#[test]
+fn integer_keys()
+{
+    let map: BTreeMap<i32, bool> = from_canonical_str(r#"{"-7":true,"0":false,"7":true}"#).unwrap();
+    assert_eq!(map, BTreeMap::from([(-7, true), (0, false), (7, true)]));
+
+    // Any other spelling of an integer is another key for it
+    for (json, offset) in [(r#"{"0":true,"007":true}"#, 10), (r#"{"+7":true}"#, 1), (r#"{"-0":true}"#, 1)]
+    {
+        assert_eq!(
+            error(from_canonical_str::<BTreeMap<i32, bool>>(json)),
+            CanonicalError::NotCanonical { offset, reason: "Integer key is not in canonical form" },
+            "{}", json,
+        );
+    }
+
+    let CanonicalError::Deserialize { offset, .. } = error(from_canonical_str::<BTreeMap<u8, bool>>(r#"{"1":true,"x":true}"#)) else { panic!() };
+    assert_eq!(offset, 10);
+}
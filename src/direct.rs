@@ -0,0 +1,539 @@
+//! # Direct canonicalization
+//!
+//! Canonicalizes JSON text straight from its bytes to [OLPC Canonical JSON](https://wiki.laptop.org/go/Canonical_JSON),
+//! without a [serde_json::Value] or a formatter in between.
+//!
+//! The input is read once into a flat list of offsets, each object's members are sorted by those
+//! offsets, and the output is copied from the input wherever it is canonical already. Only strings
+//! with escapes and `-0` are rewritten. The bytes are the same as those of [canonicalize_slice](crate::canonicalize_slice)
+//! with [CanonicalFormatter::new](crate::CanonicalFormatter::new), which is the one to use for other
+//! profiles and rules.
+//!
+//! Duplicate keys pass through, all of them in the order they appear, as they do in [canonicalize_slice](crate::canonicalize_slice).
+//! Parsing into a [serde_json::Value] keeps only the last one instead, so the two only agree on input
+//! without duplicates. Use [IJsonPolicy](crate::IJsonPolicy) with the formatter to reject them.
+//!
+//! ```rust
+//! use serde_canonical_json::direct;
+//!
+//! let json = r#"{ "b": [1, -0, "é"], "a": { "d": null, "c": true } }"#;
+//!
+//! assert_eq!(direct::canonicalize(json.as_bytes()).unwrap(), r#"{"a":{"c":true,"d":null},"b":[1,0,"é"]}"#.as_bytes());
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use crate::CanonicalError;
+use crate::text::decode_string;
+
+
+/// A value or key found in the input.
+#[derive(Clone, Copy)]
+enum Node
+{
+    /// An object, whose keys and values follow it up to the node at `end`.
+    Object { end: usize },
+    /// An array, whose elements follow it up to the node at `end`.
+    Array { end: usize },
+    /// Bytes of the input that are canonical already.
+    Input { start: usize, end: usize },
+    /// Bytes that were rewritten into the arena.
+    Arena { start: usize, end: usize },
+}
+
+
+/// An object or array partly written out.
+enum Frame
+{
+    /// Sorted pairs of key and value nodes.
+    Object { members: Vec<(usize, usize)>, position: usize },
+    Array { start: usize, next: usize, end: usize },
+}
+
+
+/// Canonicalizes JSON text given as bytes, which must be UTF-8.
+pub fn canonicalize(json: &[u8]) -> io::Result<Vec<u8>>
+{
+    let mut tape = Tape { input: json, position: 0, nodes: Vec::new(), arena: Vec::new() };
+    tape.parse()?;
+
+    let mut output = Vec::with_capacity(json.len());
+    tape.write(&mut output);
+
+    Ok(output)
+}
+
+
+/// The nodes of a parsed input, in the order they appear.
+struct Tape<'a>
+{
+    input: &'a [u8],
+    position: usize,
+    nodes: Vec<Node>,
+    arena: Vec<u8>,
+}
+
+
+impl Tape<'_>
+{
+    /// Reads a single JSON value spanning the whole input.
+    fn parse(&mut self) -> io::Result<()>
+    {
+        // An explicit stack of open containers rather than recursion, so deep input cannot overflow the call stack
+        let mut stack = Vec::new();
+
+        loop
+        {
+            self.value(&mut stack)?;
+
+            loop
+            {
+                let Some(&open) = stack.last() else
+                {
+                    self.skip_whitespace();
+
+                    if self.position < self.input.len()
+                    {
+                        return Err(self.error("Unexpected characters after the value"))
+                    }
+
+                    return Ok(())
+                };
+
+                let object = matches!(self.nodes[open], Node::Object { .. });
+
+                self.skip_whitespace();
+
+                match (object, self.next_byte())
+                {
+                    (_, None) => return Err(self.end_of_input()),
+                    (true, Some(b',')) =>
+                    {
+                        self.key()?;
+                        break
+                    }
+                    (false, Some(b',')) => break,
+                    (true, Some(b'}')) | (false, Some(b']')) =>
+                    {
+                        stack.pop();
+                        self.close(open);
+                    }
+                    (true, _) => return Err(self.error_before("Expected ',' or '}'")),
+                    (false, _) => return Err(self.error_before("Expected ',' or ']'")),
+                }
+            }
+        }
+    }
+
+
+    /// Reads the start of a value, leaving any container it opens on the stack.
+    fn value(&mut self, stack: &mut Vec<usize>) -> io::Result<()>
+    {
+        loop
+        {
+            self.skip_whitespace();
+
+            let start = self.position;
+
+            match self.next_byte()
+            {
+                Some(b'{') =>
+                {
+                    let open = self.open(Node::Object { end: 0 });
+                    self.skip_whitespace();
+
+                    if self.skip_byte(b'}')
+                    {
+                        self.close(open);
+                        return Ok(())
+                    }
+
+                    stack.push(open);
+                    self.key()?;
+                }
+                Some(b'[') =>
+                {
+                    let open = self.open(Node::Array { end: 0 });
+                    self.skip_whitespace();
+
+                    if self.skip_byte(b']')
+                    {
+                        self.close(open);
+                        return Ok(())
+                    }
+
+                    stack.push(open);
+                }
+                Some(b'"') => return self.string(start),
+                Some(b'-' | b'0'..=b'9') => return self.number(start),
+                Some(b't') => return self.literal(start, b"true"),
+                Some(b'f') => return self.literal(start, b"false"),
+                Some(b'n') => return self.literal(start, b"null"),
+                None => return Err(self.end_of_input()),
+                _ => return Err(self.error_before("Expected a value")),
+            }
+        }
+    }
+
+
+    /// Reads `"key":`, leaving the position at the member's value.
+    fn key(&mut self) -> io::Result<()>
+    {
+        self.skip_whitespace();
+
+        let start = self.position;
+
+        match self.next_byte()
+        {
+            Some(b'"') => self.string(start)?,
+            None => return Err(self.end_of_input()),
+            _ => return Err(self.error_before("Expected a string key")),
+        }
+
+        self.skip_whitespace();
+
+        match self.next_byte()
+        {
+            Some(b':') => Ok(()),
+            None => Err(self.end_of_input()),
+            _ => Err(self.error_before("Expected ':'")),
+        }
+    }
+
+
+    /// Reads the rest of a string whose opening quote is at `start`.
+    fn string(&mut self, start: usize) -> io::Result<()>
+    {
+        // Without escapes, the string is canonical as it is
+        while let Some(&byte) = self.input.get(self.position)
+        {
+            match byte
+            {
+                b'"' =>
+                {
+                    self.position += 1;
+
+                    if std::str::from_utf8(&self.input[start + 1..self.position - 1]).is_err()
+                    {
+                        return Err(self.error("String is not valid UTF-8"))
+                    }
+
+                    self.nodes.push(Node::Input { start, end: self.position });
+
+                    return Ok(())
+                }
+                b'\\' => break,
+                0x00..=0x1F => return Err(self.error("Control character in string")),
+                _ => self.position += 1,
+            }
+        }
+
+        let (text, end) = decode_string(self.input, start)?;
+        self.position = end;
+
+        let arena_start = self.arena.len();
+        self.arena.push(b'"');
+
+        for byte in text.bytes()
+        {
+            if matches!(byte, b'"' | b'\\')
+            {
+                self.arena.push(b'\\');
+            }
+
+            self.arena.push(byte);
+        }
+
+        self.arena.push(b'"');
+        self.nodes.push(Node::Arena { start: arena_start, end: self.arena.len() });
+
+        Ok(())
+    }
+
+
+    /// Reads a number starting at `start`, which must be an integer.
+    fn number(&mut self, start: usize) -> io::Result<()>
+    {
+        self.position = start;
+        self.skip_byte(b'-');
+
+        match self.next_byte()
+        {
+            Some(b'0') => (),
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.error_before("Invalid number")),
+        }
+
+        // Only a leading zero can be followed by more digits
+        if self.input.get(self.position).is_some_and(u8::is_ascii_digit)
+        {
+            return Err(self.error("Invalid number"))
+        }
+
+        let integer_end = self.position;
+
+        if self.skip_byte(b'.')
+        {
+            self.required_digits()?;
+        }
+
+        if self.skip_byte(b'e') || self.skip_byte(b'E')
+        {
+            let _ = self.skip_byte(b'+') || self.skip_byte(b'-');
+            self.required_digits()?;
+        }
+
+        if self.position > integer_end
+        {
+            // Only ASCII was consumed
+            let text = std::str::from_utf8(&self.input[start..self.position]).unwrap_or_default();
+
+            return match text.parse::<f64>()
+            {
+                Ok(value) if value.is_finite() => Err(Error::new(ErrorKind::InvalidData, "Floating point numbers are forbidden.")),
+                _ => Err(CanonicalError::InvalidJson { offset: start, reason: "Number is out of range" }.into()),
+            }
+        }
+
+        if &self.input[start..self.position] == b"-0"
+        {
+            self.arena.push(b'0');
+            self.nodes.push(Node::Arena { start: self.arena.len() - 1, end: self.arena.len() });
+        }
+        else
+        {
+            self.nodes.push(Node::Input { start, end: self.position });
+        }
+
+        Ok(())
+    }
+
+
+    fn literal(&mut self, start: usize, literal: &[u8]) -> io::Result<()>
+    {
+        if !self.input[start..].starts_with(literal)
+        {
+            return Err(CanonicalError::InvalidJson { offset: start, reason: "Expected a value" }.into())
+        }
+
+        self.position = start + literal.len();
+        self.nodes.push(Node::Input { start, end: self.position });
+
+        Ok(())
+    }
+
+
+    fn open(&mut self, node: Node) -> usize
+    {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+
+    /// Ends the container at `open` after the last node read.
+    fn close(&mut self, open: usize)
+    {
+        let last = self.nodes.len();
+
+        if let Node::Object { end } | Node::Array { end } = &mut self.nodes[open]
+        {
+            *end = last;
+        }
+    }
+
+
+    /// Writes the nodes out, with the members of each object sorted by their keys.
+    fn write(&self, output: &mut Vec<u8>)
+    {
+        let mut stack = Vec::new();
+        let mut next = Some(0);
+
+        loop
+        {
+            if let Some(index) = next.take()
+            {
+                match self.nodes[index]
+                {
+                    Node::Object { end } =>
+                    {
+                        output.push(b'{');
+                        stack.push(Frame::Object { members: self.members(index, end), position: 0 });
+                    }
+                    Node::Array { end } =>
+                    {
+                        output.push(b'[');
+                        stack.push(Frame::Array { start: index + 1, next: index + 1, end });
+                    }
+                    node => output.extend_from_slice(self.bytes(node)),
+                }
+            }
+
+            let Some(frame) = stack.last_mut() else
+            {
+                return
+            };
+
+            match frame
+            {
+                Frame::Object { members, position } => match members.get(*position)
+                {
+                    Some(&(key, value)) =>
+                    {
+                        if *position > 0
+                        {
+                            output.push(b',');
+                        }
+
+                        output.extend_from_slice(self.bytes(self.nodes[key]));
+                        output.push(b':');
+
+                        *position += 1;
+                        next = Some(value);
+                    }
+                    None =>
+                    {
+                        output.push(b'}');
+                        stack.pop();
+                    }
+                },
+                Frame::Array { start, next: element, end } =>
+                {
+                    if *element == *end
+                    {
+                        output.push(b']');
+                        stack.pop();
+                        continue
+                    }
+
+                    if *element > *start
+                    {
+                        output.push(b',');
+                    }
+
+                    next = Some(*element);
+                    *element = self.after(*element);
+                }
+            }
+        }
+    }
+
+
+    /// The key and value nodes of the object at `index`, in canonical order.
+    fn members(&self, index: usize, end: usize) -> Vec<(usize, usize)>
+    {
+        let mut members = Vec::new();
+        let mut key = index + 1;
+
+        while key < end
+        {
+            members.push((key, key + 1));
+            key = self.after(key + 1);
+        }
+
+        // Keys are compared as written, closing quote included, like the formatter's OLPC order
+        members.sort_by(|(a, _), (b, _)| self.bytes(self.nodes[*a])[1..].cmp(&self.bytes(self.nodes[*b])[1..]));
+
+        members
+    }
+
+
+    /// The node after the value at `index` and everything inside it.
+    fn after(&self, index: usize) -> usize
+    {
+        match self.nodes[index]
+        {
+            Node::Object { end } | Node::Array { end } => end,
+            _ => index + 1,
+        }
+    }
+
+
+    fn bytes(&self, node: Node) -> &[u8]
+    {
+        match node
+        {
+            Node::Input { start, end } => &self.input[start..end],
+            Node::Arena { start, end } => &self.arena[start..end],
+            Node::Object { .. } | Node::Array { .. } => &[],
+        }
+    }
+
+
+    fn next_byte(&mut self) -> Option<u8>
+    {
+        let byte = self.input.get(self.position).copied();
+
+        if byte.is_some()
+        {
+            self.position += 1;
+        }
+
+        byte
+    }
+
+
+    fn skip_byte(&mut self, expected: u8) -> bool
+    {
+        let found = self.input.get(self.position) == Some(&expected);
+
+        if found
+        {
+            self.position += 1;
+        }
+
+        found
+    }
+
+
+    fn skip_digits(&mut self)
+    {
+        while self.input.get(self.position).is_some_and(u8::is_ascii_digit)
+        {
+            self.position += 1;
+        }
+    }
+
+
+    fn required_digits(&mut self) -> io::Result<()>
+    {
+        if !self.input.get(self.position).is_some_and(u8::is_ascii_digit)
+        {
+            return Err(self.error("Invalid number"))
+        }
+
+        self.skip_digits();
+
+        Ok(())
+    }
+
+
+    fn skip_whitespace(&mut self)
+    {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.position)
+        {
+            self.position += 1;
+        }
+    }
+
+
+    fn error(&self, reason: &'static str) -> io::Error
+    {
+        CanonicalError::InvalidJson { offset: self.position, reason }.into()
+    }
+
+
+    /// An error about the byte that was just consumed.
+    fn error_before(&self, reason: &'static str) -> io::Error
+    {
+        CanonicalError::InvalidJson { offset: self.position.saturating_sub(1), reason }.into()
+    }
+
+
+    fn end_of_input(&self) -> io::Error
+    {
+        CanonicalError::InvalidJson { offset: self.input.len(), reason: "Unexpected end of input" }.into()
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,63 @@
+use crate::{CanonicalError, CanonicalFormatter, canonicalize_slice, direct::canonicalize};
+
+
+#[test]
+fn matches_formatter()
+{
+    for json in
+    [
+        r#" { "c" : [ 1, -0, 18446744073709551616, -12, true, false, null, [ ], { } ], "b" : { "z" : "😀", "y" : { } }, "a" : "x\ty\/z\u0001\"\\" } "#,
+        r#"{"b":1,"a\u0000":2,"a":3,"a\"":4,"a!":5,"a\\":6,"\u00e9":7,"é ":8}"#,
+        r#"{"a":1,"a":2,"A":[{"z":{"y":{"x":[]}}}]}"#,
+        r#""\ud83d\ude00 \u2028 ﬁ""#,
+        "123456789012345678901234567890",
+        "[[[[]],[[{}]]]]",
+        " null ",
+    ]
+    {
+        assert_eq!(canonicalize(json.as_bytes()).unwrap(), canonicalize_slice(json.as_bytes(), CanonicalFormatter::new()).unwrap(), "{}", json);
+    }
+}
+
+
+#[test]
+fn duplicate_keys()
+{
+    let json = br#"{"b":1,"a":2,"b":0,"a":{"z":1,"z":[]}}"#;
+
+    // Every duplicate is kept, in input order, where a Value keeps only the last
+    assert_eq!(canonicalize(json).unwrap(), br#"{"a":2,"a":{"z":1,"z":[]},"b":1,"b":0}"#);
+    assert_eq!(canonicalize(json).unwrap(), canonicalize_slice(json, CanonicalFormatter::new()).unwrap());
+
+    let value: serde_json::Value = serde_json::from_slice(json).unwrap();
+    assert_eq!(value, serde_json::json!({ "a": { "z": [] }, "b": 0 }));
+}
+
+
+#[test]
+fn deep_nesting()
+{
+    let json = format!("{}0{}", "[{\"a\":".repeat(100_000), "}]".repeat(100_000));
+
+    assert_eq!(canonicalize(json.as_bytes()).unwrap(), json.as_bytes());
+}
+
+
+#[test]
+fn errors()
+{
+    for json in ["", "[1,]", "{\"a\" 1}", "[01]", "\"\\x\"", "\"a", "1 2", "[1e400]", "tru", "{\"a\":1", "[\"\\ud800\"]", "\"a\u{1}\"", "{1:2}"]
+    {
+        let direct = canonicalize(json.as_bytes()).unwrap_err();
+        let text = canonicalize_slice(json.as_bytes(), CanonicalFormatter::new()).unwrap_err();
+
+        assert!(CanonicalError::from_io(&direct).is_some(), "{}", json);
+        assert_eq!(CanonicalError::from_io(&direct), CanonicalError::from_io(&text), "{}", json);
+    }
+
+    let error = canonicalize(b"[1.5]").unwrap_err();
+    assert_eq!(error.to_string(), "Floating point numbers are forbidden.");
+
+    let error = canonicalize(b"\"\xff\"").unwrap_err();
+    assert!(matches!(CanonicalError::from_io(&error), Some(CanonicalError::InvalidJson { reason: "String is not valid UTF-8", .. })));
+}
@@ -0,0 +1,88 @@
+//! Typed errors, carried inside the [std::io::Error]s that the formatter returns.
+
+use std::{fmt, io::{self, ErrorKind}};
+
+
+/// A value that the formatter refused to write.
+///
+/// The formatter returns [io::Error]s with [ErrorKind::InvalidData], as serde_json requires; this is
+/// their inner error, reached with [CanonicalError::from_io].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CanonicalError
+{
+    /// An object has two members with the same key.
+    DuplicateKey(String),
+    /// An object key arrived out of order where keys were promised to be sorted already.
+    UnsortedKey(String),
+    /// A string contains a UTF-16 surrogate that is not part of a pair.
+    LoneSurrogate(u16),
+    /// A string contains a Unicode noncharacter.
+    Noncharacter(char),
+    /// An integer is outside of the range that IEEE 754 doubles represent exactly.
+    UnsafeInteger(String),
+    /// A key or string is not in Unicode Normalization Form C.
+    NotNormalized,
+    /// A float is NaN or infinite, which the profile has no way to write.
+    NonFiniteFloat,
+    /// Objects and arrays nest deeper than the limit given.
+    TooDeep(usize),
+    /// An object has more members than the limit given.
+    TooManyMembers(usize),
+    /// A key or string is longer than the limit given, in bytes.
+    StringTooLong(usize),
+    /// More bytes are buffered than the limit given.
+    TooLarge(usize),
+    /// JSON text could not be parsed, for the reason given at the byte offset given.
+    InvalidJson { offset: usize, reason: &'static str },
+    /// JSON text is not in canonical form, for the reason given at the byte offset given.
+    NotCanonical { offset: usize, reason: &'static str },
+    /// The type being deserialized rejected a value, with the message given, at the byte offset given.
+    Deserialize { offset: usize, message: String },
+}
+
+
+impl CanonicalError
+{
+    /// The typed error inside an [io::Error] returned by this crate, if it has one.
+    pub fn from_io(error: &io::Error) -> Option<&Self>
+    {
+        error.get_ref().and_then(|inner| inner.downcast_ref())
+    }
+}
+
+
+impl fmt::Display for CanonicalError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Self::DuplicateKey(key) => write!(f, "Object has duplicate key {:?}.", key),
+            Self::UnsortedKey(key) => write!(f, "Object key {:?} is out of order.", key),
+            Self::LoneSurrogate(unit) => write!(f, "String contains lone surrogate U+{:04X}.", unit),
+            Self::Noncharacter(ch) => write!(f, "String contains noncharacter U+{:04X}.", *ch as u32),
+            Self::UnsafeInteger(digits) => write!(f, "Integer {} is outside of the I-JSON safe range.", digits),
+            Self::NotNormalized => write!(f, "String is not in Unicode Normalization Form C."),
+            Self::NonFiniteFloat => write!(f, "Float is NaN or infinite."),
+            Self::TooDeep(max) => write!(f, "Nesting is deeper than the limit of {}.", max),
+            Self::TooManyMembers(max) => write!(f, "Object has more members than the limit of {}.", max),
+            Self::StringTooLong(max) => write!(f, "String is longer than the limit of {} bytes.", max),
+            Self::TooLarge(max) => write!(f, "Output is larger than the limit of {} buffered bytes.", max),
+            Self::InvalidJson { offset, reason } | Self::NotCanonical { offset, reason } => write!(f, "{} at byte {}.", reason, offset),
+            Self::Deserialize { offset, message } => write!(f, "{} at byte {}.", message, offset),
+        }
+    }
+}
+
+
+impl std::error::Error for CanonicalError {}
+
+
+impl From<CanonicalError> for io::Error
+{
+    fn from(error: CanonicalError) -> Self
+    {
+        io::Error::new(ErrorKind::InvalidData, error)
+    }
+}
@@ -0,0 +1,161 @@
+//! Float formatting for the profiles that permit floating point numbers.
+
+
+/// The shortest round trip digits of a finite float, with the decimal point placed before `digits`
+/// and then moved `point` places to the right.
+struct Decomposed
+{
+    negative: bool,
+    digits: String,
+    point: i32,
+}
+
+
+impl Decomposed
+{
+    /// Takes apart the output of the `{:e}` format, which is the shortest round trip representation.
+    fn new(scientific: &str) -> Self
+    {
+        let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific, "0"));
+        let negative = mantissa.starts_with('-');
+        let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+
+        Self { negative, digits, point: exponent.parse::<i32>().unwrap_or(0) + 1 }
+    }
+
+
+    /// Writes `d.ddd` followed by `e`, the exponent sign if `plus` or negative, and at least `width` exponent digits.
+    fn exponential(&self, output: &mut String, plus: bool, width: usize)
+    {
+        output.push_str(&self.digits[..1]);
+
+        if self.digits.len() > 1
+        {
+            output.push('.');
+            output.push_str(&self.digits[1..]);
+        }
+
+        let exponent = self.point - 1;
+        output.push('e');
+
+        if exponent < 0
+        {
+            output.push('-');
+        }
+        else if plus
+        {
+            output.push('+');
+        }
+
+        output.push_str(&format!("{:0width$}", exponent.unsigned_abs(), width = width));
+    }
+
+
+    /// Writes the digits in positional notation, with `.0` appended to integers if `point_zero`.
+    fn positional(&self, output: &mut String, point_zero: bool)
+    {
+        let length = self.digits.len() as i32;
+
+        if self.point <= 0
+        {
+            output.push_str("0.");
+            output.extend(std::iter::repeat_n('0', self.point.unsigned_abs() as usize));
+            output.push_str(&self.digits);
+        }
+        else if self.point >= length
+        {
+            output.push_str(&self.digits);
+            output.extend(std::iter::repeat_n('0', (self.point - length) as usize));
+
+            if point_zero
+            {
+                output.push_str(".0");
+            }
+        }
+        else
+        {
+            output.push_str(&self.digits[..self.point as usize]);
+            output.push('.');
+            output.push_str(&self.digits[self.point as usize..]);
+        }
+    }
+}
+
+
+/// Formats a finite float the way Python's `repr` does.
+pub(crate) fn python_repr(value: f64) -> String
+{
+    let decomposed = Decomposed::new(&format!("{:e}", value));
+    let mut output = String::new();
+
+    if decomposed.negative
+    {
+        output.push('-');
+    }
+
+    if decomposed.point <= -4 || decomposed.point > 16
+    {
+        decomposed.exponential(&mut output, true, 2);
+    }
+    else
+    {
+        decomposed.positional(&mut output, true);
+    }
+
+    output
+}
+
+
+/// Formats a finite float the way ECMAScript's `Number::toString` does.
+pub(crate) fn ecmascript(value: f64) -> String
+{
+    if value == 0.0
+    {
+        return "0".to_owned()
+    }
+
+    let decomposed = Decomposed::new(&format!("{:e}", value));
+    let mut output = String::new();
+
+    if decomposed.negative
+    {
+        output.push('-');
+    }
+
+    if -6 < decomposed.point && decomposed.point <= 21
+    {
+        decomposed.positional(&mut output, false);
+    }
+    else
+    {
+        decomposed.exponential(&mut output, true, 1);
+    }
+
+    output
+}
+
+
+/// Formats a finite float the way Go's `encoding/json` does, from its `{:e}` output so that `float32`
+/// values keep their own shortest digits.
+pub(crate) fn go(scientific: &str) -> String
+{
+    let decomposed = Decomposed::new(scientific);
+    let mut output = String::new();
+
+    if decomposed.negative
+    {
+        output.push('-');
+    }
+
+    // Zero has a single digit, so it always lands in the positional branch
+    if decomposed.digits == "0" || (-6 < decomposed.point && decomposed.point <= 21)
+    {
+        decomposed.positional(&mut output, false);
+    }
+    else
+    {
+        decomposed.exponential(&mut output, true, 1);
+    }
+
+    output
+}
@@ -0,0 +1,80 @@
+//! Hints that serde adapters in this crate leave for the [CanonicalFormatter](crate::CanonicalFormatter)
+//! writing their value, as serde tells a formatter nothing about the Rust types behind what it writes.
+
+use std::cell::Cell;
+
+
+thread_local!
+{
+    static SET: Cell<Option<bool>> = const { Cell::new(None) };
+    static UNTYPED: Cell<usize> = const { Cell::new(0) };
+    static NON_FINITE: Cell<bool> = const { Cell::new(false) };
+}
+
+
+/// Runs `serialize` with the array it begins marked as a set, whose elements the formatter sorts by
+/// their bytes and, with `dedup`, writes once each.
+pub(crate) fn set<R>(dedup: bool, serialize: impl FnOnce() -> R) -> R
+{
+    SET.set(Some(dedup));
+    let _reset = Reset(|| SET.set(None));
+
+    serialize()
+}
+
+
+/// Takes the set mark for the array that is beginning, so the arrays inside it are not marked too.
+pub(crate) fn take_set() -> Option<bool>
+{
+    SET.take()
+}
+
+
+/// Runs `serialize` with its integers marked as untyped, so profiles that quote integers by their Rust
+/// type leave them as they are.
+#[cfg(feature = "cosmos")]
+pub(crate) fn untyped<R>(serialize: impl FnOnce() -> R) -> R
+{
+    UNTYPED.set(UNTYPED.get() + 1);
+    let _reset = Reset(|| UNTYPED.set(UNTYPED.get() - 1));
+
+    serialize()
+}
+
+
+/// Whether the integer being written comes from an untyped value.
+pub(crate) fn in_untyped() -> bool
+{
+    UNTYPED.get() > 0
+}
+
+
+/// Runs `serialize` with the float it writes marked as NaN or infinite, which serde_json writes as a null.
+#[cfg(feature = "ipld")]
+pub(crate) fn non_finite<R>(serialize: impl FnOnce() -> R) -> R
+{
+    NON_FINITE.set(true);
+    let _reset = Reset(|| NON_FINITE.set(false));
+
+    serialize()
+}
+
+
+/// Takes the mark for a null that stands in for a NaN or infinite float.
+pub(crate) fn take_non_finite() -> bool
+{
+    NON_FINITE.take()
+}
+
+
+/// Runs its closure when dropped, so a hint is cleared even if serialization panics.
+struct Reset<F: FnMut()>(F);
+
+
+impl<F: FnMut()> Drop for Reset<F>
+{
+    fn drop(&mut self)
+    {
+        (self.0)()
+    }
+}
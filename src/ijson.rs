@@ -0,0 +1,233 @@
+//! Checks for the [I-JSON](https://www.rfc-editor.org/rfc/rfc7493) restrictions.
+
+use std::{collections::HashSet, fmt, io};
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use crate::CanonicalError;
+
+
+/// The largest magnitude an integer can have and still be exactly representable as a double.
+pub(crate) const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+
+pub(crate) fn is_noncharacter(ch: char) -> bool
+{
+    let code_point = ch as u32;
+
+    (0xFDD0..=0xFDEF).contains(&code_point) || code_point & 0xFFFE == 0xFFFE
+}
+
+
+/// Checks every string, key and integer in a raw JSON fragment, which is written as-is.
+pub(crate) fn check_raw_fragment(fragment: &str) -> io::Result<()>
+{
+    check_surrogates(fragment)?;
+
+    let Checked(result) = serde_json::from_str(fragment)?;
+    result?;
+
+    Ok(check_integers(fragment)?)
+}
+
+
+/// Checks integers by their digits, as serde_json reads those beyond 64 bits as floats.
+fn check_integers(fragment: &str) -> Result<(), CanonicalError>
+{
+    let mut rest = fragment;
+
+    while let Some(index) = rest.find(|ch: char| ch == '"' || ch == '-' || ch.is_ascii_digit())
+    {
+        rest = &rest[index..];
+
+        if let Some(string) = rest.strip_prefix('"')
+        {
+            // Skips to the closing quote, past any escaped one
+            let mut escaped = false;
+
+            rest = "";
+
+            for (end, ch) in string.char_indices()
+            {
+                if ch == '"' && !escaped
+                {
+                    rest = &string[end + 1..];
+                    break
+                }
+
+                escaped = ch == '\\' && !escaped;
+            }
+
+            continue
+        }
+
+        let end = rest.find(|ch: char| !matches!(ch, '0'..='9' | '-' | '+' | '.' | 'e' | 'E')).unwrap_or(rest.len());
+        let number = &rest[..end];
+
+        rest = &rest[end..];
+
+        if !number.contains(['.', 'e', 'E'])
+        {
+            // Digits beyond the range of u128 are certainly unsafe too
+            let magnitude = number.trim_start_matches('-').parse().unwrap_or(u128::MAX);
+
+            CheckVisitor::integer(magnitude, number.to_owned())?;
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Raw fragments can spell out surrogates with `\u` escapes, which serde_json would not report as such.
+fn check_surrogates(fragment: &str) -> Result<(), CanonicalError>
+{
+    let unit = |escape: &str| escape.strip_prefix("\\u")
+        .and_then(|hex| hex.get(..4))
+        .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+
+    let mut rest = fragment;
+
+    while let Some(index) = rest.find('\\')
+    {
+        rest = &rest[index..];
+
+        let Some(high) = unit(rest) else
+        {
+            // Any other escape is two characters long, and may itself escape a backslash
+            rest = rest.get(2..).unwrap_or("");
+            continue
+        };
+
+        rest = &rest[6..];
+
+        if (0xDC00..0xE000).contains(&high)
+        {
+            return Err(CanonicalError::LoneSurrogate(high))
+        }
+
+        if (0xD800..0xDC00).contains(&high)
+        {
+            match unit(rest)
+            {
+                Some(low) if (0xDC00..0xE000).contains(&low) => rest = &rest[6..],
+                _ => return Err(CanonicalError::LoneSurrogate(high)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Walks a JSON value, keeping the first I-JSON violation rather than failing the parse.
+struct Checked(Result<(), CanonicalError>);
+
+
+impl<'de> Deserialize<'de> for Checked
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(CheckVisitor)
+    }
+}
+
+
+struct CheckVisitor;
+
+
+impl CheckVisitor
+{
+    fn string(value: &str) -> Result<(), CanonicalError>
+    {
+        match value.chars().find(|ch| is_noncharacter(*ch))
+        {
+            Some(ch) => Err(CanonicalError::Noncharacter(ch)),
+            None => Ok(()),
+        }
+    }
+
+
+    fn integer(magnitude: u128, digits: String) -> Result<(), CanonicalError>
+    {
+        if magnitude > MAX_SAFE_INTEGER
+        {
+            Err(CanonicalError::UnsafeInteger(digits))
+        }
+        else
+        {
+            Ok(())
+        }
+    }
+}
+
+
+impl<'de> Visitor<'de> for CheckVisitor
+{
+    type Value = Checked;
+
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str("a JSON value")
+    }
+
+
+    fn visit_bool<E>(self, _value: bool) -> Result<Checked, E> { Ok(Checked(Ok(()))) }
+
+
+    fn visit_unit<E>(self) -> Result<Checked, E> { Ok(Checked(Ok(()))) }
+
+
+    fn visit_f64<E>(self, _value: f64) -> Result<Checked, E> { Ok(Checked(Ok(()))) }
+
+
+    fn visit_i64<E>(self, _value: i64) -> Result<Checked, E> { Ok(Checked(Ok(()))) }
+
+
+    fn visit_u64<E>(self, _value: u64) -> Result<Checked, E> { Ok(Checked(Ok(()))) }
+
+
+    fn visit_str<E>(self, value: &str) -> Result<Checked, E>
+    {
+        Ok(Checked(Self::string(value)))
+    }
+
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Checked, A::Error>
+        where
+            A: SeqAccess<'de>,
+    {
+        let mut result = Ok(());
+
+        while let Some(Checked(element)) = seq.next_element()?
+        {
+            result = result.and(element);
+        }
+
+        Ok(Checked(result))
+    }
+
+
+    fn visit_map<A>(self, mut map: A) -> Result<Checked, A::Error>
+        where
+            A: MapAccess<'de>,
+    {
+        let mut result = Ok(());
+        let mut keys = HashSet::new();
+
+        while let Some(key) = map.next_key::<String>()?
+        {
+            let Checked(value) = map.next_value()?;
+
+            result = result.and(Self::string(&key)).and(value);
+
+            if !keys.insert(key.clone())
+            {
+                result = result.and(Err(CanonicalError::DuplicateKey(key)));
+            }
+        }
+
+        Ok(Checked(result))
+    }
+}
@@ -0,0 +1,423 @@
+//! # IPLD DAG-JSON
+//!
+//! Serializes values as [DAG-JSON](https://ipld.io/specs/codecs/dag-json/spec/) with [Profile::DagJson],
+//! and computes content identifiers for them.
+//!
+//! Links to other blocks are written as `{"/":"<cid>"}` by serializing a [Cid], and byte strings are
+//! written as `{"/":{"bytes":"<base64>"}}` by anything that serializes as bytes, such as [Bytes].
+//!
+//! ```rust
+//! use serde_json::json;
+//! use serde_canonical_json::ipld::{self, Cid};
+//!
+//! let parent = Cid::for_value(&json!({ "name": "parent" })).unwrap();
+//! let child = json!({ "parent": parent, "name": "child" });
+//!
+//! let bytes = ipld::to_vec(&child).unwrap();
+//! assert!(String::from_utf8(bytes).unwrap().starts_with(r#"{"name":"child","parent":{"/":"bagu"#));
+//! ```
+
+use std::{fmt, io::{self, ErrorKind, Error}, str::FromStr};
+use serde::{Serialize, Serializer, Deserialize, Deserializer, de, ser::SerializeMap};
+use sha2::{Digest, Sha256};
+use crate::{CanonicalFormatter, Profile, to_vec_with_formatter};
+use finite::Finite;
+
+mod finite;
+
+
+/// The multicodec code for DAG-JSON.
+pub const DAG_JSON: u64 = 0x0129;
+
+/// The multihash code for SHA2-256.
+pub const SHA2_256: u64 = 0x12;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+
+/// Serializes `value` as DAG-JSON.
+///
+/// # Errors
+///
+/// Fails with [CanonicalError::NonFiniteFloat](crate::CanonicalError::NonFiniteFloat) for NaN and
+/// infinite floats, which DAG-JSON cannot represent.
+pub fn to_vec<T>(value: &T) -> io::Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+{
+    to_vec_with_formatter(&Finite(value), CanonicalFormatter::with_profile(Profile::DagJson))
+}
+
+
+/// A version 1 content identifier.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Cid
+{
+    codec: u64,
+    hash_code: u64,
+    digest: Vec<u8>,
+}
+
+
+impl Cid
+{
+    /// Creates a CID from its codec and multihash parts.
+    pub fn new_v1(codec: u64, hash_code: u64, digest: Vec<u8>) -> Self
+    {
+        Self { codec, hash_code, digest }
+    }
+
+
+    /// Creates the CID of a block of DAG-JSON bytes, using SHA2-256.
+    pub fn from_dag_json(bytes: &[u8]) -> Self
+    {
+        Self::new_v1(DAG_JSON, SHA2_256, Sha256::digest(bytes).to_vec())
+    }
+
+
+    /// Serializes `value` as DAG-JSON and returns the CID of the resulting block.
+    pub fn for_value<T>(value: &T) -> io::Result<Self>
+        where
+            T: ?Sized + Serialize,
+    {
+        Ok(Self::from_dag_json(&to_vec(value)?))
+    }
+
+
+    pub fn codec(&self) -> u64 { self.codec }
+
+
+    pub fn hash_code(&self) -> u64 { self.hash_code }
+
+
+    pub fn digest(&self) -> &[u8] { &self.digest }
+
+
+    /// The binary form of the CID: version, codec and multihash.
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut bytes = Vec::with_capacity(self.digest.len() + 8);
+
+        write_varint(&mut bytes, 1);
+        write_varint(&mut bytes, self.codec);
+        write_varint(&mut bytes, self.hash_code);
+        write_varint(&mut bytes, self.digest.len() as u64);
+        bytes.extend_from_slice(&self.digest);
+
+        bytes
+    }
+
+
+    /// Parses the binary form of a CID.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self>
+    {
+        let (codec, hash_code, digest) = decode_cid_bytes(bytes)?;
+
+        Ok(Self::new_v1(codec, hash_code, digest))
+    }
+}
+
+
+impl fmt::Display for Cid
+{
+    /// Formats the CID as a base32 multibase string.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let bytes = self.to_bytes();
+
+        let mut output = String::with_capacity(1 + (bytes.len() * 8).div_ceil(5));
+        output.push('b');
+
+        let mut buffer = 0u32;
+        let mut bits = 0;
+
+        for byte in bytes
+        {
+            buffer = buffer << 8 | byte as u32;
+            bits += 8;
+
+            while bits >= 5
+            {
+                bits -= 5;
+                output.push(BASE32_ALPHABET[(buffer >> bits & 0x1F) as usize] as char);
+            }
+        }
+
+        if bits > 0
+        {
+            output.push(BASE32_ALPHABET[(buffer << (5 - bits) & 0x1F) as usize] as char);
+        }
+
+        f.write_str(&output)
+    }
+}
+
+
+impl FromStr for Cid
+{
+    type Err = Error;
+
+
+    /// Parses a base32 multibase CID string.
+    fn from_str(s: &str) -> io::Result<Self>
+    {
+        let (codec, hash_code, digest) = decode_cid(s)?;
+
+        Ok(Self::new_v1(codec, hash_code, digest))
+    }
+}
+
+
+impl Serialize for Cid
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("/", &self.to_string())?;
+        map.end()
+    }
+}
+
+
+impl<'de> Deserialize<'de> for Cid
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+    {
+        struct LinkVisitor;
+
+        impl<'de> de::Visitor<'de> for LinkVisitor
+        {
+            type Value = Cid;
+
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result
+            {
+                f.write_str("a DAG-JSON link")
+            }
+
+
+            fn visit_map<A>(self, mut map: A) -> Result<Cid, A::Error>
+                where
+                    A: de::MapAccess<'de>,
+            {
+                let (Some("/"), Some(cid)) = (map.next_key::<String>()?.as_deref(), map.next_value::<String>().ok()) else
+                {
+                    return Err(de::Error::custom("link must have a single \"/\" member"))
+                };
+
+                if map.next_key::<de::IgnoredAny>()?.is_some()
+                {
+                    return Err(de::Error::custom("link must have a single \"/\" member"))
+                }
+
+                cid.parse().map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_map(LinkVisitor)
+    }
+}
+
+
+/// A byte string, written as `{"/":{"bytes":"<base64>"}}` in DAG-JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Bytes(pub Vec<u8>);
+
+
+impl Serialize for Bytes
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+
+impl<'de> Deserialize<'de> for Bytes
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct BytesMember
+        {
+            bytes: String,
+        }
+
+        struct BytesVisitor;
+
+        impl<'de> de::Visitor<'de> for BytesVisitor
+        {
+            type Value = Bytes;
+
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result
+            {
+                f.write_str("DAG-JSON bytes")
+            }
+
+
+            fn visit_map<A>(self, mut map: A) -> Result<Bytes, A::Error>
+                where
+                    A: de::MapAccess<'de>,
+            {
+                let (Some("/"), Some(member)) = (map.next_key::<String>()?.as_deref(), map.next_value::<BytesMember>().ok()) else
+                {
+                    return Err(de::Error::custom("bytes must have a single \"/\" member holding {\"bytes\":\"<base64>\"}"))
+                };
+
+                if map.next_key::<de::IgnoredAny>()?.is_some()
+                {
+                    return Err(de::Error::custom("bytes must have a single \"/\" member holding {\"bytes\":\"<base64>\"}"))
+                }
+
+                decode_base64(&member.bytes).map(Bytes).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_map(BytesVisitor)
+    }
+}
+
+
+/// Decodes standard unpadded base64, as DAG-JSON writes bytes.
+fn decode_base64(encoded: &str) -> io::Result<Vec<u8>>
+{
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for ch in encoded.bytes()
+    {
+        let Some(value) = ALPHABET.iter().position(|candidate| *candidate == ch) else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Bytes contain a character outside of unpadded base64."))
+        };
+
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+
+        if bits >= 8
+        {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    if bits >= 6 || buffer & ((1 << bits) - 1) != 0
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Bytes are not canonical base64."))
+    }
+
+    Ok(bytes)
+}
+
+
+/// Decodes a base32 multibase CID string into its codec, multihash code and digest.
+pub(crate) fn decode_cid(cid: &str) -> io::Result<(u64, u64, Vec<u8>)>
+{
+    let Some(encoded) = cid.strip_prefix('b') else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "CID must use base32 multibase encoding."))
+    };
+
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for ch in encoded.bytes()
+    {
+        let Some(value) = BASE32_ALPHABET.iter().position(|candidate| *candidate == ch) else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "CID contains a character outside of base32."))
+        };
+
+        buffer = buffer << 5 | value as u32;
+        bits += 5;
+
+        if bits >= 8
+        {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    // Bits left over from the last character are padding, so only zeros give the one spelling of a CID
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "CID has trailing base32 bits."))
+    }
+
+    decode_cid_bytes(&bytes)
+}
+
+
+/// Decodes the binary form of a CID: version, codec and multihash.
+fn decode_cid_bytes(mut bytes: &[u8]) -> io::Result<(u64, u64, Vec<u8>)>
+{
+    if read_varint(&mut bytes)? != 1
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Only version 1 CIDs are supported."))
+    }
+
+    let codec = read_varint(&mut bytes)?;
+    let hash_code = read_varint(&mut bytes)?;
+    let length = read_varint(&mut bytes)?;
+
+    if bytes.len() as u64 != length
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "CID digest length does not match."))
+    }
+
+    Ok((codec, hash_code, bytes.to_vec()))
+}
+
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64)
+{
+    while value >= 0x80
+    {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+
+fn read_varint(bytes: &mut &[u8]) -> io::Result<u64>
+{
+    let mut value = 0u64;
+
+    for shift in (0..63).step_by(7)
+    {
+        let Some((byte, rest)) = bytes.split_first() else
+        {
+            break
+        };
+
+        *bytes = rest;
+        value |= ((byte & 0x7F) as u64) << shift;
+
+        if byte & 0x80 == 0
+        {
+            return Ok(value)
+        }
+    }
+
+    Err(Error::new(ErrorKind::InvalidData, "CID contains a truncated varint."))
+}
+
+
+#[cfg(test)]
+mod tests;
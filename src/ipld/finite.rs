@@ -0,0 +1,240 @@
+//! A serializer that forwards everything to another one, marking NaN and infinite floats so the
+//! [CanonicalFormatter](crate::CanonicalFormatter) can reject the `null` that serde_json writes for them.
+
+use serde::{Serialize, Serializer, ser};
+use crate::hint;
+
+
+/// Wraps a serializer, or a value to serialize with one.
+pub(super) struct Finite<T>(pub(super) T);
+
+
+impl<T> Serialize for Finite<&T>
+    where
+        T: ?Sized + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        self.0.serialize(Finite(serializer))
+    }
+}
+
+
+impl<S> Serializer for Finite<S>
+    where
+        S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Finite<S::SerializeSeq>;
+    type SerializeTuple = Finite<S::SerializeTuple>;
+    type SerializeTupleStruct = Finite<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Finite<S::SerializeTupleVariant>;
+    type SerializeMap = Finite<S::SerializeMap>;
+    type SerializeStruct = Finite<S::SerializeStruct>;
+    type SerializeStructVariant = Finite<S::SerializeStructVariant>;
+
+    fn serialize_bool(self, v: bool) -> Result<S::Ok, S::Error> { self.0.serialize_bool(v) }
+    fn serialize_i8(self, v: i8) -> Result<S::Ok, S::Error> { self.0.serialize_i8(v) }
+    fn serialize_i16(self, v: i16) -> Result<S::Ok, S::Error> { self.0.serialize_i16(v) }
+    fn serialize_i32(self, v: i32) -> Result<S::Ok, S::Error> { self.0.serialize_i32(v) }
+    fn serialize_i64(self, v: i64) -> Result<S::Ok, S::Error> { self.0.serialize_i64(v) }
+    fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> { self.0.serialize_i128(v) }
+    fn serialize_u8(self, v: u8) -> Result<S::Ok, S::Error> { self.0.serialize_u8(v) }
+    fn serialize_u16(self, v: u16) -> Result<S::Ok, S::Error> { self.0.serialize_u16(v) }
+    fn serialize_u32(self, v: u32) -> Result<S::Ok, S::Error> { self.0.serialize_u32(v) }
+    fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> { self.0.serialize_u64(v) }
+    fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> { self.0.serialize_u128(v) }
+    fn serialize_char(self, v: char) -> Result<S::Ok, S::Error> { self.0.serialize_char(v) }
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> { self.0.serialize_str(v) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<S::Ok, S::Error> { self.0.serialize_bytes(v) }
+    fn serialize_none(self) -> Result<S::Ok, S::Error> { self.0.serialize_none() }
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> { self.0.serialize_unit() }
+    fn is_human_readable(&self) -> bool { self.0.is_human_readable() }
+
+    fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error>
+    {
+        if !v.is_finite()
+        {
+            return hint::non_finite(|| self.0.serialize_f32(v));
+        }
+
+        self.0.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error>
+    {
+        if !v.is_finite()
+        {
+            return hint::non_finite(|| self.0.serialize_f64(v));
+        }
+
+        self.0.serialize_f64(v)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<S::Ok, S::Error>
+        where
+            T: ?Sized + Serialize,
+    {
+        self.0.serialize_some(&Finite(value))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error>
+    {
+        self.0.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<S::Ok, S::Error>
+    {
+        self.0.serialize_unit_variant(name, index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error>
+        where
+            T: ?Sized + Serialize,
+    {
+        self.0.serialize_newtype_struct(name, &Finite(value))
+    }
+
+    fn serialize_newtype_variant<T>(self, name: &'static str, index: u32, variant: &'static str, value: &T) -> Result<S::Ok, S::Error>
+        where
+            T: ?Sized + Serialize,
+    {
+        self.0.serialize_newtype_variant(name, index, variant, &Finite(value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error>
+    {
+        self.0.serialize_seq(len).map(Finite)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error>
+    {
+        self.0.serialize_tuple(len).map(Finite)
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error>
+    {
+        self.0.serialize_tuple_struct(name, len).map(Finite)
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, S::Error>
+    {
+        self.0.serialize_tuple_variant(name, index, variant, len).map(Finite)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error>
+    {
+        self.0.serialize_map(len).map(Finite)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error>
+    {
+        self.0.serialize_struct(name, len).map(Finite)
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant, S::Error>
+    {
+        self.0.serialize_struct_variant(name, index, variant, len).map(Finite)
+    }
+}
+
+
+/// Forwards a compound serializer that takes elements, wrapping each of them.
+macro_rules! forward_elements
+{
+    ($($trait:ident :: $method:ident),*) =>
+    {
+        $(
+            impl<S> ser::$trait for Finite<S>
+                where
+                    S: ser::$trait,
+            {
+                type Ok = S::Ok;
+                type Error = S::Error;
+
+                fn $method<T>(&mut self, value: &T) -> Result<(), S::Error>
+                    where
+                        T: ?Sized + Serialize,
+                {
+                    self.0.$method(&Finite(value))
+                }
+
+                fn end(self) -> Result<S::Ok, S::Error>
+                {
+                    self.0.end()
+                }
+            }
+        )*
+    };
+}
+
+
+/// Forwards a compound serializer that takes named fields, wrapping each of their values.
+macro_rules! forward_fields
+{
+    ($($trait:ident),*) =>
+    {
+        $(
+            impl<S> ser::$trait for Finite<S>
+                where
+                    S: ser::$trait,
+            {
+                type Ok = S::Ok;
+                type Error = S::Error;
+
+                fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error>
+                    where
+                        T: ?Sized + Serialize,
+                {
+                    self.0.serialize_field(key, &Finite(value))
+                }
+
+                fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error>
+                {
+                    self.0.skip_field(key)
+                }
+
+                fn end(self) -> Result<S::Ok, S::Error>
+                {
+                    self.0.end()
+                }
+            }
+        )*
+    };
+}
+
+
+forward_elements!(SerializeSeq::serialize_element, SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field, SerializeTupleVariant::serialize_field);
+forward_fields!(SerializeStruct, SerializeStructVariant);
+
+
+impl<S> ser::SerializeMap for Finite<S>
+    where
+        S: ser::SerializeMap,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), S::Error>
+        where
+            T: ?Sized + Serialize,
+    {
+        self.0.serialize_key(&Finite(key))
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), S::Error>
+        where
+            T: ?Sized + Serialize,
+    {
+        self.0.serialize_value(&Finite(value))
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error>
+    {
+        self.0.end()
+    }
+}
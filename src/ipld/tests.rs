@@ -0,0 +1,115 @@
+use serde::Serialize;
+use serde_json::json;
+use crate::{CanonicalError, ipld::{self, Bytes, Cid, DAG_JSON, SHA2_256}};
+
+
+// tests/vectors/ipld.py regenerates the expected CIDs with Python's hashlib and base64 modules
+
+const PARENT_CID: &str = "baguqeerabelqo2cb3ehofpug4hari32ja6j62ffiuc6vt6jpxntnlrzhni4q";
+
+
+#[derive(Serialize)]
+struct Block
+{
+    #[serde(rename = "tab\t")]
+    tab: String,
+    ccc: Bytes,
+    b: f64,
+    aa: Cid,
+}
+
+
+#[test]
+fn dag_json_block()
+{
+    let parent = Cid::for_value(&json!({ "name": "parent" })).unwrap();
+    assert_eq!(parent.to_string(), PARENT_CID);
+    assert_eq!(parent.codec(), DAG_JSON);
+    assert_eq!(parent.hash_code(), SHA2_256);
+
+    let block = Block { tab: "line\n\u{1}".to_owned(), ccc: Bytes(vec![0, 1, 2, 255]), b: 1.5, aa: parent };
+
+    let bytes = ipld::to_vec(&block).unwrap();
+    assert_eq!(
+        String::from_utf8(bytes).unwrap(),
+        format!(r#"{{"b":1.5,"aa":{{"/":"{}"}},"ccc":{{"/":{{"bytes":"AAEC/w"}}}},"tab\t":"line\n\u0001"}}"#, PARENT_CID),
+    );
+
+    assert_eq!(Cid::for_value(&block).unwrap().to_string(), "baguqeeraw4edx63d3dqgqqezqhshcjtij32shi5iybe7hzlagajssgl7osoa");
+}
+
+
+#[test]
+fn cid_round_trip()
+{
+    let cid: Cid = PARENT_CID.parse().unwrap();
+    assert_eq!(cid.to_string(), PARENT_CID);
+    assert_eq!(Cid::from_bytes(&cid.to_bytes()).unwrap(), cid);
+
+    let link: Cid = serde_json::from_value(json!({ "/": PARENT_CID })).unwrap();
+    assert_eq!(link, cid);
+
+    assert!("QmPK1s3pNYLi9ERiq3BDxKa4XosgWwFRQUydHUtz4YgpqB".parse::<Cid>().is_err());
+
+    // The last character carries two bits of padding, which must be zero, and no further character fits
+    let nonzero_padding = format!("{}r", &PARENT_CID[..PARENT_CID.len() - 1]);
+    assert!(nonzero_padding.parse::<Cid>().is_err());
+    assert!(format!("{}a", PARENT_CID).parse::<Cid>().is_err());
+    assert!(ipld::to_vec(&json!({ "/": nonzero_padding })).is_err());
+}
+
+
+#[test]
+fn reserved_key()
+{
+    assert!(ipld::to_vec(&json!({ "/": "not a link", "other": 1 })).is_err());
+    assert!(ipld::to_vec(&json!({ "/": { "bytes": "AA", "extra": 1 } })).is_err());
+    assert!(ipld::to_vec(&json!({ "/": 5 })).is_err());
+    assert!(ipld::to_vec(&json!({ "/": "not a link" })).is_err());
+    assert!(ipld::to_vec(&json!({ "/": "QmPK1s3pNYLi9ERiq3BDxKa4XosgWwFRQUydHUtz4YgpqB" })).is_err());
+    assert!(ipld::to_vec(&json!({ "/": &PARENT_CID[..PARENT_CID.len() - 2] })).is_err());
+    assert!(ipld::to_vec(&json!({ "/": PARENT_CID })).is_ok());
+}
+
+
+#[test]
+fn bytes_round_trip()
+{
+    let bytes = Bytes(vec![0, 1, 2, 255]);
+    let encoded = ipld::to_vec(&bytes).unwrap();
+    assert_eq!(encoded, br#"{"/":{"bytes":"AAEC/w"}}"#);
+    assert_eq!(serde_json::from_slice::<Bytes>(&encoded).unwrap(), bytes);
+
+    for length in 0..=4
+    {
+        let bytes = Bytes((0..length).map(|byte| byte * 67).collect());
+        assert_eq!(serde_json::from_slice::<Bytes>(&ipld::to_vec(&bytes).unwrap()).unwrap(), bytes);
+    }
+
+    assert!(serde_json::from_value::<Bytes>(json!({ "/": { "bytes": "AAEC/w==" } })).is_err());
+    assert!(serde_json::from_value::<Bytes>(json!({ "/": { "bytes": "AAEC/x" } })).is_err());
+    assert!(serde_json::from_value::<Bytes>(json!({ "/": { "bytes": "AA", "extra": 1 } })).is_err());
+    assert!(serde_json::from_value::<Bytes>(json!({ "/": PARENT_CID })).is_err());
+}
+
+
+#[test]
+fn non_finite_floats()
+{
+    #[derive(Serialize)]
+    struct Reading
+    {
+        value: Option<f32>,
+        unit: Option<String>,
+    }
+
+    let error = ipld::to_vec(&[f64::NAN, 1.5]).unwrap_err();
+    assert_eq!(CanonicalError::from_io(&error), Some(&CanonicalError::NonFiniteFloat));
+
+    let error = ipld::to_vec(&Reading { value: Some(f32::NEG_INFINITY), unit: None }).unwrap_err();
+    assert_eq!(CanonicalError::from_io(&error), Some(&CanonicalError::NonFiniteFloat));
+
+    // Real nulls are still written, including after a rejected float
+    assert_eq!(ipld::to_vec(&Reading { value: None, unit: None }).unwrap(), br#"{"unit":null,"value":null}"#);
+    assert_eq!(ipld::to_vec(&[None, Some(1.5)]).unwrap(), b"[null,1.5]");
+}
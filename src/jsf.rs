@@ -0,0 +1,259 @@
+//! # Embedded clear-text signatures
+//!
+//! Signs JSON documents in the style of the [JSON Signature Format](https://cyberphone.github.io/doc/security/jsf.html),
+//! where the signature object is embedded in the document it signs.
+//!
+//! The signature covers the [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785) canonical form of the
+//! whole document, written with [Profile::Jcs](crate::Profile::Jcs), including the signature object
+//! with its `value` member removed. A signature property can hold a single signature, a list of independent
+//! `signers`, or a `chain` where every signature also covers the signatures before it.
+//!
+//! ```rust
+//! use ed25519_dalek::SigningKey;
+//! use serde_json::json;
+//! use serde_canonical_json::jsf;
+//!
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//! let mut document = json!({ "statement": "Hello", "count": 3 });
+//!
+//! jsf::sign_embedded(&mut document, "signature", &key).unwrap();
+//! assert_eq!(document["signature"]["algorithm"], "Ed25519");
+//!
+//! let signers = jsf::verify_embedded(&document, "signature").unwrap();
+//! assert_eq!(signers, vec![key.verifying_key()]);
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde_json::{json, Map, Value};
+use crate::{CanonicalFormatter, Profile, to_vec_with_formatter};
+
+
+/// The JSF algorithm identifier for signatures produced by this module.
+pub const ALGORITHM: &str = "Ed25519";
+
+
+/// Signs `document` and stores a single signature object under `property`.
+///
+/// Any existing value of `property` is replaced.
+pub fn sign_embedded(document: &mut Value, property: &str, key: &SigningKey) -> io::Result<()>
+{
+    let object = document_object(document)?;
+
+    object.insert(property.to_owned(), unsigned_signature(key));
+
+    let signature = sign_document(document, key)?;
+
+    document_object(document)?[property]["value"] = Value::from(signature);
+
+    Ok(())
+}
+
+
+/// Signs `document` as one more independent signer, appending to the `signers` list under `property`.
+///
+/// Each signer covers the document with only its own signature object in the list.
+pub fn add_signer(document: &mut Value, property: &str, key: &SigningKey) -> io::Result<()>
+{
+    add_to_list(document, property, "signers", key)
+}
+
+
+/// Signs `document` as the next link of the `chain` under `property`.
+///
+/// Each signature covers the document including every signature before it in the chain.
+pub fn append_to_chain(document: &mut Value, property: &str, key: &SigningKey) -> io::Result<()>
+{
+    add_to_list(document, property, "chain", key)
+}
+
+
+/// Verifies every signature stored under `property` and returns the signers' public keys in order.
+///
+/// The keys are taken from the signature objects themselves, so callers must still decide whether
+/// they trust them.
+pub fn verify_embedded(document: &Value, property: &str) -> io::Result<Vec<VerifyingKey>>
+{
+    let Some(signature) = document.get(property).and_then(Value::as_object) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature property is missing or not an object."))
+    };
+
+    let list = ["signers", "chain"].into_iter().find_map(|name| signature.get(name).map(|list| (name, list)));
+
+    let Some((name, list)) = list else
+    {
+        return Ok(vec![verify_one(document.clone(), property, None)?])
+    };
+
+    let Some(list) = list.as_array().filter(|list| !list.is_empty()) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature list must be a non-empty array."))
+    };
+
+    let mut keys = Vec::with_capacity(list.len());
+
+    for index in 0..list.len()
+    {
+        // Independent signers only see themselves, chained signers see everything before them
+        let (covered, position) = if name == "chain"
+        {
+            (list[..=index].to_vec(), index)
+        }
+        else
+        {
+            (vec![list[index].clone()], 0)
+        };
+
+        let mut document = document.clone();
+        document[property][name] = Value::Array(covered);
+
+        keys.push(verify_one(document, property, Some((name, position)))?);
+    }
+
+    Ok(keys)
+}
+
+
+fn add_to_list(document: &mut Value, property: &str, name: &str, key: &SigningKey) -> io::Result<()>
+{
+    let object = document_object(document)?;
+
+    let container = object.entry(property).or_insert_with(|| json!({ name: [] }));
+
+    let Some(list) = container.get_mut(name).and_then(Value::as_array_mut) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature property does not hold a signature list of this kind."))
+    };
+
+    let previous = std::mem::take(list);
+    let mut covered = if name == "chain"
+    {
+        previous.clone()
+    }
+    else
+    {
+        Vec::new()
+    };
+    covered.push(unsigned_signature(key));
+
+    let mut unsigned = document.clone();
+    unsigned[property][name] = Value::Array(covered);
+
+    let signature = sign_document(&unsigned, key)?;
+
+    let mut signed = unsigned_signature(key);
+    signed["value"] = Value::from(signature);
+
+    let mut list = previous;
+    list.push(signed);
+
+    document[property][name] = Value::Array(list);
+
+    Ok(())
+}
+
+
+fn verify_one(mut document: Value, property: &str, position: Option<(&str, usize)>) -> io::Result<VerifyingKey>
+{
+    let signature = match position
+    {
+        Some((name, index)) => &mut document[property][name][index],
+        None => &mut document[property],
+    };
+
+    let Some(signature) = signature.as_object_mut() else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature must be an object."))
+    };
+
+    if signature.get("algorithm").and_then(Value::as_str) != Some(ALGORITHM)
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature algorithm must be Ed25519."))
+    }
+
+    let key = public_key(signature.get("publicKey"))?;
+
+    let Some(Value::String(value)) = signature.remove("value") else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature value is missing."))
+    };
+
+    let Ok(value) = Signature::from_slice(&decode(&value)?) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature value is not an Ed25519 signature."))
+    };
+
+    let canonical = to_vec_with_formatter(&document, CanonicalFormatter::with_profile(Profile::Jcs))?;
+
+    if key.verify_strict(&canonical, &value).is_err()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature is invalid."))
+    }
+
+    Ok(key)
+}
+
+
+fn sign_document(document: &Value, key: &SigningKey) -> io::Result<String>
+{
+    let canonical = to_vec_with_formatter(document, CanonicalFormatter::with_profile(Profile::Jcs))?;
+
+    Ok(URL_SAFE_NO_PAD.encode(key.sign(&canonical).to_bytes()))
+}
+
+
+fn unsigned_signature(key: &SigningKey) -> Value
+{
+    json!({
+        "algorithm": ALGORITHM,
+        "publicKey": {
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes()),
+        },
+    })
+}
+
+
+fn public_key(jwk: Option<&Value>) -> io::Result<VerifyingKey>
+{
+    let Some(jwk) = jwk.and_then(Value::as_object) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature public key is missing."))
+    };
+
+    if jwk.get("kty").and_then(Value::as_str) != Some("OKP") || jwk.get("crv").and_then(Value::as_str) != Some("Ed25519")
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature public key must be an Ed25519 key."))
+    }
+
+    let Some(x) = jwk.get("x").and_then(Value::as_str) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature public key is missing x."))
+    };
+
+    let Ok(bytes) = <[u8; 32]>::try_from(decode(x)?) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Signature public key must be 32 bytes."))
+    };
+
+    VerifyingKey::from_bytes(&bytes).map_err(|_| Error::new(ErrorKind::InvalidData, "Signature public key is invalid."))
+}
+
+
+fn document_object(document: &mut Value) -> io::Result<&mut Map<String, Value>>
+{
+    document.as_object_mut().ok_or_else(|| Error::new(ErrorKind::InvalidData, "Signed document must be an object."))
+}
+
+
+fn decode(encoded: &str) -> io::Result<Vec<u8>>
+{
+    URL_SAFE_NO_PAD.decode(encoded).map_err(|_| Error::new(ErrorKind::InvalidData, "Signature member is not valid base64url."))
+}
+
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,87 @@
+use ed25519_dalek::SigningKey;
+use serde_json::json;
+use crate::jsf;
+
+
+#[test]
+fn single_signature()
+{
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let mut document = json!({ "statement": "Hello", "count": 3 });
+
+    jsf::sign_embedded(&mut document, "signature", &key).unwrap();
+
+    // tests/vectors/jsf.py regenerates the expected signature with Python's `cryptography` package
+    assert_eq!(document, json!({
+        "statement": "Hello",
+        "count": 3,
+        "signature": {
+            "algorithm": "Ed25519",
+            "publicKey": { "kty": "OKP", "crv": "Ed25519", "x": "6kpsY-KcUgq-9VB7Ey7F-ZVHdq6-vnuSQh7qaRRG0iw" },
+            "value": "vD9fPi69HB9YmpFDS6-oZvb1gWWlz4L8A2oLkh1G7bdsSAjRXRw6dADTCDr19rKYD3zrmKkqnricsZKMRtJsCg"
+        }
+    }));
+
+    assert_eq!(jsf::verify_embedded(&document, "signature").unwrap(), vec![key.verifying_key()]);
+
+    document["count"] = json!(4);
+    assert!(jsf::verify_embedded(&document, "signature").is_err());
+}
+
+
+#[test]
+fn multiple_signers()
+{
+    let first = SigningKey::from_bytes(&[7; 32]);
+    let second = SigningKey::from_bytes(&[8; 32]);
+    let mut document = json!({ "statement": "Hello" });
+
+    jsf::add_signer(&mut document, "signature", &first).unwrap();
+    jsf::add_signer(&mut document, "signature", &second).unwrap();
+
+    assert_eq!(jsf::verify_embedded(&document, "signature").unwrap(), vec![first.verifying_key(), second.verifying_key()]);
+
+    // Independent signers can be removed without invalidating the others
+    document["signature"]["signers"].as_array_mut().unwrap().remove(0);
+    assert_eq!(jsf::verify_embedded(&document, "signature").unwrap(), vec![second.verifying_key()]);
+}
+
+
+#[test]
+fn signature_chain()
+{
+    let first = SigningKey::from_bytes(&[7; 32]);
+    let second = SigningKey::from_bytes(&[8; 32]);
+    let mut document = json!({ "statement": "Hello" });
+
+    jsf::append_to_chain(&mut document, "signature", &first).unwrap();
+    jsf::append_to_chain(&mut document, "signature", &second).unwrap();
+
+    assert_eq!(jsf::verify_embedded(&document, "signature").unwrap(), vec![first.verifying_key(), second.verifying_key()]);
+
+    // Later links cover earlier ones, so dropping the first link breaks the chain
+    document["signature"]["chain"].as_array_mut().unwrap().remove(0);
+    assert!(jsf::verify_embedded(&document, "signature").is_err());
+}
+
+
+#[test]
+fn signs_jcs()
+{
+    use ed25519_dalek::Signature;
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let mut document = json!({ "amount": 2.5, "note": "a\nb", "😀": 1, "ﬁ": 2 });
+
+    jsf::sign_embedded(&mut document, "signature", &key).unwrap();
+
+    // Canonical bytes from the RFC 8785 reference implementation in tests/fixtures/jcs.js
+    let canonical = r#"{"amount":2.5,"note":"a\nb","signature":{"algorithm":"Ed25519","publicKey":{"crv":"Ed25519","kty":"OKP","x":"6kpsY-KcUgq-9VB7Ey7F-ZVHdq6-vnuSQh7qaRRG0iw"}},"😀":1,"ﬁ":2}"#;
+
+    let value = URL_SAFE_NO_PAD.decode(document["signature"]["value"].as_str().unwrap()).unwrap();
+    let signature = Signature::from_slice(&value).unwrap();
+
+    key.verifying_key().verify_strict(canonical.as_bytes(), &signature).unwrap();
+    assert_eq!(jsf::verify_embedded(&document, "signature").unwrap(), vec![key.verifying_key()]);
+}
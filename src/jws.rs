@@ -0,0 +1,399 @@
+//! # JSON Web Signatures over canonical JSON
+//!
+//! Builds and verifies [JWS](https://www.rfc-editor.org/rfc/rfc7515) objects whose payload is the
+//! canonical serialization of a value, so every producer signs the same bytes for the same object.
+//!
+//! Both the compact and the general JSON serializations are supported, as are detached payloads
+//! and the unencoded payload option (`"b64": false`) from [RFC 7797](https://www.rfc-editor.org/rfc/rfc7797).
+//!
+//! Verification re-canonicalizes the payload before checking the signature, so a payload that was
+//! re-encoded in transit still verifies as long as it describes the same object.
+//!
+//! ```rust
+//! use ed25519_dalek::SigningKey;
+//! use serde_json::json;
+//! use serde_canonical_json::jws::{self, PayloadMode};
+//!
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//! let payload = json!({ "b": 2, "a": 1 });
+//!
+//! let token = jws::sign_compact(&payload, PayloadMode::Unencoded, &key).unwrap();
+//! assert!(token.contains(".."));
+//!
+//! // The receiver parsed the body with members in a different order
+//! let received = json!({ "a": 1, "b": 2 });
+//! jws::verify_compact_detached(&token, &received, &key.verifying_key()).unwrap();
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+use crate::{CanonicalFormatter, to_vec_with_formatter};
+
+
+/// Produces JWS signatures for a single algorithm.
+pub trait JwsSigner
+{
+    /// The `alg` header parameter for signatures produced by this signer.
+    fn algorithm(&self) -> &str;
+
+    /// The `kid` header parameter, if the signer should identify its key.
+    fn key_id(&self) -> Option<&str> { None }
+
+    /// Signs the JWS signing input.
+    fn sign(&self, signing_input: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+
+/// Checks JWS signatures for a single algorithm.
+pub trait JwsVerifier
+{
+    /// The `alg` header parameter accepted by this verifier.
+    fn algorithm(&self) -> &str;
+
+    /// The `kid` header parameter, if only signatures with a matching key id should be checked.
+    fn key_id(&self) -> Option<&str> { None }
+
+    /// Checks `signature` over the JWS signing input.
+    fn verify(&self, signing_input: &[u8], signature: &[u8]) -> io::Result<()>;
+}
+
+
+impl JwsSigner for ed25519_dalek::SigningKey
+{
+    fn algorithm(&self) -> &str { "EdDSA" }
+
+
+    fn sign(&self, signing_input: &[u8]) -> io::Result<Vec<u8>>
+    {
+        Ok(ed25519_dalek::Signer::sign(self, signing_input).to_bytes().to_vec())
+    }
+}
+
+
+impl JwsVerifier for ed25519_dalek::VerifyingKey
+{
+    fn algorithm(&self) -> &str { "EdDSA" }
+
+
+    fn verify(&self, signing_input: &[u8], signature: &[u8]) -> io::Result<()>
+    {
+        let Ok(signature) = ed25519_dalek::Signature::from_slice(signature) else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Signature is not an Ed25519 signature."))
+        };
+
+        self.verify_strict(signing_input, &signature).map_err(|_| Error::new(ErrorKind::InvalidData, "Signature is invalid."))
+    }
+}
+
+
+/// How the payload is carried in a JWS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PayloadMode
+{
+    /// The base64url encoded payload is included in the JWS.
+    #[default]
+    Attached,
+    /// The payload is base64url encoded for signing, but left out of the JWS.
+    Detached,
+    /// The payload is signed as-is with `"b64": false` and left out of the JWS, per RFC 7797.
+    Unencoded,
+}
+
+
+/// A JWS using the general JSON serialization.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JwsJson
+{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+    pub signatures: Vec<JwsSignature>,
+}
+
+
+/// A single entry of [JwsJson::signatures].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JwsSignature
+{
+    pub protected: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<Map<String, Value>>,
+    pub signature: String,
+}
+
+
+/// Signs the canonical serialization of `payload` and returns a JWS in the compact serialization.
+///
+/// With [PayloadMode::Detached] or [PayloadMode::Unencoded] the payload section is left empty.
+pub fn sign_compact<T, S>(payload: &T, mode: PayloadMode, signer: &S) -> io::Result<String>
+    where
+        T: ?Sized + Serialize,
+        S: ?Sized + JwsSigner,
+{
+    let payload = to_vec_with_formatter(payload, CanonicalFormatter::new())?;
+    let (protected, signature) = sign_payload(&payload, mode, signer)?;
+
+    let payload = match mode
+    {
+        PayloadMode::Attached => URL_SAFE_NO_PAD.encode(&payload),
+        PayloadMode::Detached | PayloadMode::Unencoded => String::new(),
+    };
+
+    Ok(format!("{}.{}.{}", protected, payload, signature))
+}
+
+
+/// Signs the canonical serialization of `payload` once per signer and returns a JWS in the general
+/// JSON serialization.
+pub fn sign_json<T>(payload: &T, mode: PayloadMode, signers: &[&dyn JwsSigner]) -> io::Result<JwsJson>
+    where
+        T: ?Sized + Serialize,
+{
+    let payload = to_vec_with_formatter(payload, CanonicalFormatter::new())?;
+
+    let mut signatures = Vec::with_capacity(signers.len());
+
+    for signer in signers
+    {
+        let (protected, signature) = sign_payload(&payload, mode, *signer)?;
+        signatures.push(JwsSignature { protected, header: None, signature });
+    }
+
+    let payload = match mode
+    {
+        PayloadMode::Attached => Some(URL_SAFE_NO_PAD.encode(&payload)),
+        PayloadMode::Detached | PayloadMode::Unencoded => None,
+    };
+
+    Ok(JwsJson { payload, signatures })
+}
+
+
+/// Verifies a compact JWS with an attached payload and returns the payload.
+pub fn verify_compact<V>(jws: &str, verifier: &V) -> io::Result<Value>
+    where
+        V: ?Sized + JwsVerifier,
+{
+    let (protected, payload, signature) = split_compact(jws)?;
+
+    if payload.is_empty()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "JWS payload is detached."))
+    }
+
+    let payload = parse_payload(payload)?;
+    verify_signature(protected, &payload, signature, verifier)?;
+
+    Ok(payload)
+}
+
+
+/// Verifies a compact JWS whose payload was detached, using the received `payload` object.
+pub fn verify_compact_detached<T, V>(jws: &str, payload: &T, verifier: &V) -> io::Result<()>
+    where
+        T: ?Sized + Serialize,
+        V: ?Sized + JwsVerifier,
+{
+    let (protected, attached, signature) = split_compact(jws)?;
+
+    if !attached.is_empty()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "JWS payload is not detached."))
+    }
+
+    verify_signature(protected, payload, signature, verifier)
+}
+
+
+/// Verifies a JWS in the general JSON serialization with an attached payload and returns the payload.
+///
+/// Succeeds if any signature accepted by `verifier` is valid.
+pub fn verify_json<V>(jws: &JwsJson, verifier: &V) -> io::Result<Value>
+    where
+        V: ?Sized + JwsVerifier,
+{
+    let Some(payload) = &jws.payload else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "JWS payload is detached."))
+    };
+
+    let payload = parse_payload(payload)?;
+    verify_any(jws, &payload, verifier)?;
+
+    Ok(payload)
+}
+
+
+/// Verifies a JWS in the general JSON serialization whose payload was detached, using the received
+/// `payload` object.
+///
+/// Succeeds if any signature accepted by `verifier` is valid.
+pub fn verify_json_detached<T, V>(jws: &JwsJson, payload: &T, verifier: &V) -> io::Result<()>
+    where
+        T: ?Sized + Serialize,
+        V: ?Sized + JwsVerifier,
+{
+    if jws.payload.is_some()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "JWS payload is not detached."))
+    }
+
+    verify_any(jws, payload, verifier)
+}
+
+
+fn sign_payload<S>(payload: &[u8], mode: PayloadMode, signer: &S) -> io::Result<(String, String)>
+    where
+        S: ?Sized + JwsSigner,
+{
+    let mut header = Map::new();
+    header.insert("alg".to_owned(), Value::from(signer.algorithm()));
+
+    if let Some(key_id) = signer.key_id()
+    {
+        header.insert("kid".to_owned(), Value::from(key_id));
+    }
+
+    if mode == PayloadMode::Unencoded
+    {
+        header.insert("b64".to_owned(), Value::Bool(false));
+        header.insert("crit".to_owned(), Value::from(vec!["b64"]));
+    }
+
+    let protected = URL_SAFE_NO_PAD.encode(to_vec_with_formatter(&header, CanonicalFormatter::new())?);
+    let signature = signer.sign(&signing_input(&protected, payload, mode == PayloadMode::Unencoded))?;
+
+    Ok((protected, URL_SAFE_NO_PAD.encode(signature)))
+}
+
+
+fn verify_any<T, V>(jws: &JwsJson, payload: &T, verifier: &V) -> io::Result<()>
+    where
+        T: ?Sized + Serialize,
+        V: ?Sized + JwsVerifier,
+{
+    let mut result = Err(Error::new(ErrorKind::InvalidData, "JWS has no signature for this verifier."));
+
+    for signature in &jws.signatures
+    {
+        result = verify_signature(&signature.protected, payload, &signature.signature, verifier);
+
+        if result.is_ok()
+        {
+            break
+        }
+    }
+
+    result
+}
+
+
+fn verify_signature<T, V>(protected: &str, payload: &T, signature: &str, verifier: &V) -> io::Result<()>
+    where
+        T: ?Sized + Serialize,
+        V: ?Sized + JwsVerifier,
+{
+    let header: Map<String, Value> = serde_json::from_slice(&decode(protected)?)?;
+
+    if header.get("alg").and_then(Value::as_str) != Some(verifier.algorithm())
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "JWS algorithm does not match the verifier."))
+    }
+
+    if let Some(key_id) = verifier.key_id()
+    {
+        if header.get("kid").and_then(Value::as_str) != Some(key_id)
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "JWS key id does not match the verifier."))
+        }
+    }
+
+    // Every critical parameter must be present and understood, and b64 is the only one understood here
+    if let Some(critical) = header.get("crit")
+    {
+        let Some(critical) = critical.as_array().filter(|critical| !critical.is_empty()) else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "JWS crit header parameter must be a non-empty array."))
+        };
+
+        for name in critical
+        {
+            match name.as_str()
+            {
+                Some("b64") if header.contains_key("b64") => (),
+                Some("b64") => return Err(Error::new(ErrorKind::InvalidData, "JWS critical header parameter is missing.")),
+                _ => return Err(Error::new(ErrorKind::InvalidData, "JWS critical header parameter is not understood.")),
+            }
+        }
+    }
+
+    let unencoded = match header.get("b64")
+    {
+        None | Some(Value::Bool(true)) => false,
+        Some(Value::Bool(false)) =>
+        {
+            let critical = header.get("crit").and_then(Value::as_array);
+
+            if !critical.is_some_and(|critical| critical.iter().any(|name| name == "b64"))
+            {
+                return Err(Error::new(ErrorKind::InvalidData, "JWS b64 header parameter must be listed as critical."))
+            }
+
+            true
+        }
+        Some(_) => return Err(Error::new(ErrorKind::InvalidData, "JWS b64 header parameter must be a boolean.")),
+    };
+
+    let payload = to_vec_with_formatter(payload, CanonicalFormatter::new())?;
+
+    verifier.verify(&signing_input(protected, &payload, unencoded), &decode(signature)?)
+}
+
+
+fn signing_input(protected: &str, payload: &[u8], unencoded: bool) -> Vec<u8>
+{
+    let mut input = protected.as_bytes().to_vec();
+    input.push(b'.');
+
+    if unencoded
+    {
+        input.extend_from_slice(payload);
+    }
+    else
+    {
+        input.extend_from_slice(URL_SAFE_NO_PAD.encode(payload).as_bytes());
+    }
+
+    input
+}
+
+
+fn split_compact(jws: &str) -> io::Result<(&str, &str, &str)>
+{
+    let mut parts = jws.split('.');
+
+    let (Some(protected), Some(payload), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Compact JWS must have exactly three parts."))
+    };
+
+    Ok((protected, payload, signature))
+}
+
+
+fn parse_payload(payload: &str) -> io::Result<Value>
+{
+    Ok(serde_json::from_slice(&decode(payload)?)?)
+}
+
+
+fn decode(part: &str) -> io::Result<Vec<u8>>
+{
+    URL_SAFE_NO_PAD.decode(part).map_err(|_| Error::new(ErrorKind::InvalidData, "JWS part is not valid base64url."))
+}
+
+
+#[cfg(test)]
+mod tests;
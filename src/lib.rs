@@ -28,16 +28,285 @@
 //! 
 //! assert_eq!(json, r#"{"a":"Hello!","b":false,"c":120}"#);
 
-use std::{io::{self, ErrorKind, Error}, collections::VecDeque};
+use std::{borrow::Cow, io::{self, ErrorKind, Error}, collections::VecDeque, sync::Arc};
 use serde_json::ser::Formatter;
 use lazy_static::lazy_static;
 use regex::Regex;
+use pointer::{Pointer, Segment};
+
+
+mod de;
+mod error;
+mod float;
+mod hint;
+mod ijson;
+mod order;
+mod pointer;
+mod reader;
+mod text;
+mod writer;
+
+pub use de::{from_canonical_slice, from_canonical_str};
+pub use error::CanonicalError;
+pub use order::{CodePoints, KeyOrder, LengthThenBytes, NumericAware, Utf16Units, Utf8Bytes};
+pub use reader::CanonicalizingReader;
+pub use text::{canonicalize_slice, canonicalize_str};
+pub use writer::CanonicalizingWriter;
+
+#[cfg(feature = "vc")]
+pub mod vc;
+#[cfg(feature = "jws")]
+pub mod jws;
+#[cfg(feature = "jsf")]
+pub mod jsf;
+#[cfg(feature = "nostr")]
+pub mod nostr;
+#[cfg(feature = "ipld")]
+pub mod ipld;
+#[cfg(feature = "cosmos")]
+pub mod cosmos;
+#[cfg(feature = "ssb")]
+pub mod ssb;
+pub mod set;
+pub mod projection;
+pub mod direct;
 
 
 #[derive(Default)]
 pub struct CanonicalFormatter
 {
     object_stack: VecDeque<ObjectStackFrame>,
+    profile: Profile,
+    depth: usize,
+    has_value: bool,
+    ijson: Option<IJsonPolicy>,
+    escape: Option<EscapePolicy>,
+    #[cfg(feature = "nfc")]
+    normalization: Option<NormalizationPolicy>,
+    surrogates: SurrogatePolicy,
+    key_order: Option<Arc<dyn KeyOrder + Send + Sync>>,
+    array_rules: Vec<(Pointer, ArraySort)>,
+    exclusions: Vec<Pointer>,
+    selections: Vec<Pointer>,
+    elision: Option<ElisionPolicy>,
+    limits: Limits,
+    buffered: usize,
+    string_length: usize,
+    path: Vec<Segment>,
+    buffered_arrays: Vec<bool>,
+    streaming: bool,
+}
+
+
+/// Selects the serialization rules a [CanonicalFormatter] follows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Profile
+{
+    /// [OLPC Canonical JSON](https://wiki.laptop.org/go/Canonical_JSON), where only `"` and `\\` are escaped.
+    #[default]
+    Olpc,
+    /// The event serialization from [Nostr NIP-01](https://github.com/nostr-protocol/nips/blob/master/01.md),
+    /// which also escapes line feed, carriage return, tab, backspace and form feed with their short forms.
+    Nostr,
+    /// [IPLD DAG-JSON](https://ipld.io/specs/codecs/dag-json/spec/), which sorts keys by length and then
+    /// bytes, escapes control characters, allows floats, and writes bytes as `{"/":{"bytes":"..."}}`.
+    ///
+    /// The `"/"` key is reserved for links and bytes. Links are only checked to be valid CIDs with the
+    /// `ipld` feature.
+    ///
+    /// DAG-JSON has no NaN or infinite floats. `ipld::to_vec` rejects them, but serde_json turns them
+    /// into `null` before they reach a formatter used on its own.
+    DagJson,
+    /// Python's `json.dumps(value, sort_keys=True, separators=(",", ":"), ensure_ascii=...)`, which sorts
+    /// keys by code point, escapes control characters, and formats floats with `repr`.
+    ///
+    /// With `ensure_ascii`, everything outside of printable ASCII is escaped as `\uXXXX`, using surrogate
+    /// pairs above the Basic Multilingual Plane.
+    ///
+    /// Python writes non-finite floats as `NaN` and `Infinity`, but serde_json turns them into `null`
+    /// before they reach the formatter. Integer map keys are sorted as strings, where Python sorts them
+    /// numerically.
+    Python { ensure_ascii: bool },
+    /// JavaScript's `fast-json-stable-stringify`, or `JSON.stringify` with sorted keys.
+    ///
+    /// Object members whose value is `null` are omitted, as `undefined` members are in JavaScript, so
+    /// `None` fields disappear. Array elements are kept.
+    ///
+    /// | Rule               | `Olpc`                        | `JavaScript`                                 |
+    /// |--------------------|-------------------------------|----------------------------------------------|
+    /// | Key order          | Escaped key bytes             | UTF-16 code units of the key                 |
+    /// | Control characters | Written verbatim              | `\b`, `\f`, `\n`, `\r`, `\t`, others `\u00xx` |
+    /// | Floats             | Rejected                      | ECMAScript `Number::toString`                |
+    /// | `null` members     | Kept                          | Omitted                                      |
+    ///
+    /// Integers are written exactly, where JavaScript would first round anything beyond 2^53 to a
+    /// double and write 128-bit values of 10^21 or more with an exponent.
+    JavaScript,
+    /// The [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785) JSON Canonicalization Scheme, which is
+    /// [Profile::JavaScript] with `null` members kept, as `JSON.stringify` keeps them.
+    ///
+    /// Integers are written exactly, as in [Profile::JavaScript]. RFC 8785 only accepts I-JSON, so
+    /// [IJsonPolicy::Strict] rejects those a double cannot hold.
+    Jcs,
+    /// Go's `json.Marshal` of maps, which sorts keys by bytes, escapes control characters, and formats
+    /// floats with `strconv` like ECMAScript, except that negative zero stays `-0`.
+    ///
+    /// U+2028 and U+2029 are always escaped, and with `escape_html` so are `<`, `>` and `&`, matching
+    /// `Encoder::SetEscapeHTML`. Bytes are written as a padded base64 string, as Go writes `[]byte`.
+    Go { escape_html: bool },
+    /// The legacy Amino JSON that Cosmos SDK sign docs use, which is [Profile::Go] with HTML escaping and
+    /// 64-bit and wider integers written as strings.
+    ///
+    /// Only the Rust type decides how an integer is written, so amino `int32` fields need a 32-bit or
+    /// smaller type, and every integer in a [serde_json::Value] is written as a string, except in the
+    /// untyped value of a `cosmos::Msg`, which is written as it is. Floats are rejected, as Amino does
+    /// not encode them.
+    Amino,
+    /// The `JSON.stringify(message, null, 2)` form that Secure Scuttlebutt signs and hashes.
+    ///
+    /// This is the only profile that does not sort: members keep the order they are serialized in, so
+    /// struct fields keep their declaration order, and a [serde_json::Value] needs serde_json's
+    /// `preserve_order` feature or the `ssb` feature's `OrderedValue` instead. Strings and numbers are
+    /// written as in [Profile::JavaScript], but `null` members are kept, and every member and element
+    /// goes on its own line indented by two spaces.
+    Ssb,
+}
+
+
+impl Profile
+{
+    /// The order the profile sorts keys in, or `None` if it keeps them in serialization order.
+    fn key_order(self) -> Option<&'static dyn KeyOrder>
+    {
+        match self
+        {
+            Self::Olpc | Self::Nostr => Some(&order::OlpcEscaped),
+            Self::DagJson => Some(&LengthThenBytes),
+            Self::Python { .. } | Self::Go { .. } | Self::Amino => Some(&Utf8Bytes),
+            Self::JavaScript | Self::Jcs => Some(&Utf16Units),
+            Self::Ssb => None,
+        }
+    }
+}
+
+
+/// Overrides how a [CanonicalFormatter] escapes keys and strings, whatever its [Profile].
+///
+/// `"` and `\` are always escaped. Keys are ordered as if escaped under [EscapePolicy::Olpc], so the
+/// policy never changes the order of members.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum EscapePolicy
+{
+    /// Writes everything else verbatim, including control characters.
+    #[default]
+    Olpc,
+    /// [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785) escaping: `\b`, `\f`, `\n`, `\r` and `\t`,
+    /// then `\u00xx` for the remaining control characters.
+    Jcs,
+    /// [EscapePolicy::Jcs], with everything outside of ASCII written as `\uxxxx`, using surrogate pairs
+    /// above the Basic Multilingual Plane.
+    AsciiOnly,
+    /// [EscapePolicy::Jcs], with `<`, `>`, `&`, U+2028 and U+2029 written as `\uxxxx` so the output can
+    /// be embedded in HTML and JavaScript.
+    HtmlSafe,
+}
+
+
+/// Applies Unicode Normalization Form C to every key and string a [CanonicalFormatter] writes.
+///
+/// Keys are normalized before they are sorted, and raw fragments are parsed and written again so their
+/// strings are covered too.
+#[cfg(feature = "nfc")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NormalizationPolicy
+{
+    /// Converts keys and strings to NFC, so canonically equivalent text gives the same output.
+    #[default]
+    Normalize,
+    /// Rejects keys and strings that are not already NFC with [CanonicalError::NotNormalized].
+    Reject,
+}
+
+
+/// Decides what happens to lone surrogates, `\u` escapes of a UTF-16 surrogate without its other half,
+/// when JSON text is canonicalized with [canonicalize_str] or [canonicalize_slice].
+///
+/// Rust strings cannot hold lone surrogates, so serialized values never contain them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SurrogatePolicy
+{
+    /// Rejects the input with [CanonicalError::LoneSurrogate].
+    #[default]
+    Reject,
+    /// Replaces each lone surrogate with U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+    /// Keeps each lone surrogate as a lowercase `\udxxx` escape. Keys that differ only in their lone
+    /// surrogates are ordered by those escapes.
+    Preserve,
+}
+
+
+/// How a [CanonicalFormatter] reorders the elements of an array that a rule selects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArraySort
+{
+    /// Sorts elements by the bytes the formatter wrote for them, so an array that stands for a set has
+    /// a single form. With `dedup`, only the first of several identical elements is kept.
+    CanonicalBytes { dedup: bool },
+    /// Sorts objects by the value of the named member, comparing numbers by value and anything else by
+    /// its bytes. Elements without the member, or that are not objects, go last, and ties are sorted by
+    /// their bytes.
+    ByMember(String),
+}
+
+
+/// Object members that a [CanonicalFormatter] leaves out because of their value, as if absent.
+///
+/// Members are dropped once their value is written, so an object that loses all of its members is
+/// itself dropped under [ElisionPolicy::NullAndEmpty]. Array elements are always kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ElisionPolicy
+{
+    /// Drops members whose value is `null`.
+    #[default]
+    Null,
+    /// Drops members whose value is `null`, `[]` or `{}`.
+    NullAndEmpty,
+}
+
+
+/// Bounds on what a [CanonicalFormatter] accepts, for untrusted input. `None` leaves a bound off.
+///
+/// Each bound is checked as soon as it is crossed, so the formatter stops before buffering more.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Limits
+{
+    /// The deepest objects and arrays may nest, failing with [CanonicalError::TooDeep].
+    pub depth: Option<usize>,
+    /// The most members an object may have, failing with [CanonicalError::TooManyMembers].
+    pub members: Option<usize>,
+    /// The longest a key or string may be, in UTF-8 bytes before escaping, failing with
+    /// [CanonicalError::StringTooLong].
+    pub string_length: Option<usize>,
+    /// The most bytes that may be buffered for objects and sorted arrays at once, failing with
+    /// [CanonicalError::TooLarge].
+    pub buffered_bytes: Option<usize>,
+}
+
+
+/// Enforces [I-JSON](https://www.rfc-editor.org/rfc/rfc7493) on everything a [CanonicalFormatter] writes.
+///
+/// Duplicate keys, lone surrogates, noncharacters and integers beyond ±(2^53 - 1) are rejected with a
+/// [CanonicalError]. Raw fragments are checked the same way, but never rewritten.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum IJsonPolicy
+{
+    /// Rejects integers outside of the safe range.
+    #[default]
+    Strict,
+    /// Writes integers outside of the safe range as strings, as I-JSON recommends.
+    StringifyLargeIntegers,
 }
 
 
@@ -45,20 +314,166 @@ impl CanonicalFormatter
 {
     pub fn new() -> Self
     {
-        Self { object_stack: VecDeque::new() }
+        Self::default()
+    }
+
+
+    pub fn with_profile(profile: Profile) -> Self
+    {
+        Self { profile, ..Self::default() }
+    }
+
+
+    /// Escapes keys and strings with the given policy instead of the profile's own rules.
+    pub fn with_escape_policy(mut self, policy: EscapePolicy) -> Self
+    {
+        self.escape = Some(policy);
+        self
+    }
+
+
+    /// Normalizes keys and strings to NFC, or rejects those that are not.
+    #[cfg(feature = "nfc")]
+    pub fn with_normalization(mut self, policy: NormalizationPolicy) -> Self
+    {
+        self.normalization = Some(policy);
+        self
+    }
+
+
+    /// Enforces I-JSON with the given policy on top of the profile's own rules.
+    pub fn with_ijson(mut self, policy: IJsonPolicy) -> Self
+    {
+        self.ijson = Some(policy);
+        self
     }
+
+
+    /// Sorts object members with the given order instead of the profile's own, including under
+    /// [Profile::Ssb], which otherwise keeps them in serialization order.
+    pub fn with_key_order<O>(mut self, order: O) -> Self
+        where
+            O: KeyOrder + Send + Sync + 'static,
+    {
+        self.key_order = Some(Arc::new(order));
+        self
+    }
+
+
+    /// Sorts the elements of the arrays at `pointer`, a JSON pointer where a `*` segment matches any key
+    /// or index, such as `/tags` or `/groups/*/members`.
+    ///
+    /// Arrays inside raw fragments are not matched.
+    ///
+    /// # Errors
+    ///
+    /// If `pointer` is neither empty nor starts with `/`, or has a `~` that is not `~0` or `~1`.
+    pub fn with_array_sort(mut self, pointer: &str, sort: ArraySort) -> io::Result<Self>
+    {
+        self.array_rules.push((Pointer::parse(pointer)?, sort));
+        Ok(self)
+    }
+
+
+    /// Leaves out the object members at `pointer`, a JSON pointer where a `*` segment matches any key or
+    /// index, such as `/signature` or `/entries/*/unsigned`.
+    ///
+    /// Members are still serialized, then dropped before the object is written, so this works the same
+    /// for structs and maps. Members inside raw fragments are not matched.
+    ///
+    /// # Errors
+    ///
+    /// If `pointer` is neither empty nor starts with `/`, or has a `~` that is not `~0` or `~1`.
+    pub fn with_exclusion(mut self, pointer: &str) -> io::Result<Self>
+    {
+        self.exclusions.push(Pointer::parse(pointer)?);
+        Ok(self)
+    }
+
+
+    /// Keeps only the value at `pointer`, a JSON pointer where a `*` segment matches any key or index,
+    /// along with the objects and arrays that lead to it. With several selections, everything any of
+    /// them selects is kept.
+    ///
+    /// Array elements that are left out before a kept one are written as `null`, so every kept element
+    /// keeps its index, unless [CanonicalFormatter::with_array_sort] reorders the array. Values inside
+    /// raw fragments are not matched.
+    ///
+    /// # Errors
+    ///
+    /// If `pointer` is neither empty nor starts with `/`, or has a `~` that is not `~0` or `~1`.
+    pub fn with_selection(mut self, pointer: &str) -> io::Result<Self>
+    {
+        self.selections.push(Pointer::parse(pointer)?);
+        Ok(self)
+    }
+
+
+    /// Leaves out object members whose value the policy treats as absent.
+    pub fn with_elision(mut self, policy: ElisionPolicy) -> Self
+    {
+        self.elision = Some(policy);
+        self
+    }
+
+
+    /// Fails with a [CanonicalError] as soon as the output crosses one of the limits.
+    pub fn with_limits(mut self, limits: Limits) -> Self
+    {
+        self.limits = limits;
+        self
+    }
+
+
+    /// Handles lone surrogates in canonicalized JSON text with the given policy.
+    pub fn with_surrogate_policy(mut self, policy: SurrogatePolicy) -> Self
+    {
+        self.surrogates = policy;
+        self
+    }
+}
+
+
+/// Serializes `value` with the given formatter, for the feature modules.
+#[cfg(any(feature = "vc", feature = "jws", feature = "jsf", feature = "nostr", feature = "ipld", feature = "cosmos", feature = "ssb"))]
+pub(crate) fn to_vec_with_formatter<T>(value: &T, formatter: CanonicalFormatter) -> io::Result<Vec<u8>>
+    where
+        T: ?Sized + serde::Serialize,
+{
+    let mut ser = serde_json::Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser)?;
+
+    Ok(ser.into_inner())
 }
 
 
+/// A buffered object, or a buffered array whose elements are held as members without keys.
 struct ObjectStackFrame
 {
     members: Vec<ObjectMemberBuffer>,
+    array: Option<ArraySort>,
+    /// Set for an object whose members are written as they arrive, holding only the current key.
+    streamed: Option<StreamedObject>,
+}
+
+
+/// What a streamed object remembers of the members already written.
+struct StreamedObject
+{
+    previous: Option<String>,
+    members: usize,
 }
 
 
 impl ObjectStackFrame
 {
-    fn new() -> Self { Self { members: Vec::new() } }
+    fn new() -> Self { Self { members: Vec::new(), array: None, streamed: None } }
+
+
+    fn new_array(sort: Option<ArraySort>) -> Self { Self { members: Vec::new(), array: sort, streamed: None } }
+
+
+    fn new_streamed() -> Self { Self { members: Vec::new(), array: None, streamed: Some(StreamedObject { previous: None, members: 0 }) } }
 
 
     fn push_member(&mut self)
@@ -73,21 +488,169 @@ impl ObjectStackFrame
     }
 
 
-    fn string(&mut self) -> String
+    /// Writes out the object, which is nested `depth` containers deep, sorting its members with `order`.
+    fn string(&mut self, profile: Profile, order: Option<&dyn KeyOrder>, depth: usize) -> io::Result<String>
     {
         let mut output = "{".to_owned();
+        let indent = (profile == Profile::Ssb).then(|| "  ".repeat(depth + 1));
 
-        self.members.sort_by(|a, b| a.key.cmp(&b.key));
+        if profile == Profile::JavaScript
+        {
+            self.members.retain(|member| member.value != "null");
+        }
 
-        for (index, member) in self.members.iter_mut().enumerate()
+        if let Some(order) = order
         {
-            output.push_str(&member.string(index == 0));
+            // Preserved lone surrogates all decode to U+FFFD, so keys that differ only in them are put in
+            // order of their escapes first, and the stable sort below keeps that order
+            if self.members.iter().any(|member| member.key.contains("\\ud"))
+            {
+                self.members.sort_by(|a, b| a.key.cmp(&b.key));
+            }
+
+            let mut keyed: Vec<_> = self.members.drain(..).map(|member| (member.decoded_key(), member)).collect();
+            keyed.sort_by(|(a, _), (b, _)| order.compare(a, b));
+
+            self.members.extend(keyed.into_iter().map(|(_, member)| member));
+        }
+
+        if profile == Profile::DagJson
+        {
+            self.check_dag_json_reserved()?;
+        }
+
+        for (index, member) in self.members.iter().enumerate()
+        {
+            if index > 0
+            {
+                output.push(',');
+            }
+
+            if let Some(indent) = &indent
+            {
+                output.push('\n');
+                output.push_str(indent);
+            }
+
+            output.push_str(&member.key);
+            output.push_str(if indent.is_some() { ": " } else { ":" });
+            output.push_str(&member.value);
+        }
+
+        if let (Some(indent), false) = (&indent, self.members.is_empty())
+        {
+            output.push('\n');
+            output.push_str(&indent[2..]);
         }
 
         output.push('}');
 
+        Ok(output)
+    }
+
+
+    /// Writes out a buffered array, which is nested `depth` containers deep.
+    fn array_string(&mut self, profile: Profile, depth: usize) -> String
+    {
+        match &self.array
+        {
+            Some(ArraySort::CanonicalBytes { dedup }) =>
+            {
+                self.members.sort_by(|a, b| a.value.cmp(&b.value));
+
+                if *dedup
+                {
+                    self.members.dedup_by(|a, b| a.value == b.value);
+                }
+            }
+            Some(ArraySort::ByMember(_)) =>
+            {
+                self.members.sort_by(|a, b| match (&a.sort_key, &b.sort_key)
+                {
+                    (Some(a_key), Some(b_key)) => compare_values(a_key, b_key),
+                    (a_key, b_key) => b_key.is_some().cmp(&a_key.is_some()),
+                }
+                .then_with(|| a.value.cmp(&b.value)));
+            }
+            None =>
+            {
+                // Left out elements only need to hold the index of the kept ones after them
+                while self.members.last().is_some_and(|element| element.placeholder)
+                {
+                    self.members.pop();
+                }
+            }
+        }
+
+        let separator = if profile == Profile::Ssb { format!("\n{}", "  ".repeat(depth + 1)) } else { String::new() };
+
+        let mut output = "[".to_owned();
+
+        for (index, member) in self.members.iter().enumerate()
+        {
+            if index > 0
+            {
+                output.push(',');
+            }
+
+            output.push_str(&separator);
+            output.push_str(&member.value);
+        }
+
+        if profile == Profile::Ssb && !self.members.is_empty()
+        {
+            output.push('\n');
+            output.push_str(&"  ".repeat(depth));
+        }
+
+        output.push(']');
+
         output
     }
+
+
+    /// The bytes held for the object or array so far.
+    fn buffered_len(&self) -> usize
+    {
+        self.members.iter().map(ObjectMemberBuffer::len).sum()
+    }
+
+
+    fn duplicate_key(&self) -> Option<String>
+    {
+        let mut keys = std::collections::HashSet::new();
+
+        self.members.iter().map(ObjectMemberBuffer::decoded_key).find(|key| !keys.insert(key.clone()))
+    }
+
+
+    /// DAG-JSON reserves the `"/"` key for links and bytes, so any other use of it is an error.
+    fn check_dag_json_reserved(&self) -> io::Result<()>
+    {
+        let Some(member) = self.members.iter().find(|member| member.decoded_key() == "/") else
+        {
+            return Ok(())
+        };
+
+        let valid = self.members.len() == 1 && match serde_json::from_str(&member.value)
+        {
+            #[cfg(feature = "ipld")]
+            Ok(serde_json::Value::String(cid)) => return ipld::decode_cid(&cid).map(drop),
+            #[cfg(not(feature = "ipld"))]
+            Ok(serde_json::Value::String(_)) => true,
+            Ok(serde_json::Value::Object(bytes)) => bytes.len() == 1 && bytes.get("bytes").is_some_and(serde_json::Value::is_string),
+            _ => false,
+        };
+
+        if valid
+        {
+            Ok(())
+        }
+        else
+        {
+            Err(Error::new(ErrorKind::InvalidData, "The \"/\" key is reserved for links and bytes."))
+        }
+    }
 }
 
 
@@ -96,6 +659,10 @@ struct ObjectMemberBuffer
     key: String,
     value: String,
     key_finished: bool,
+    /// The value of the member that [ArraySort::ByMember] sorts by, when this is an array element.
+    sort_key: Option<String>,
+    /// Whether this is an array element that selections left out, written as `null`.
+    placeholder: bool,
 }
 
 
@@ -103,33 +670,26 @@ impl ObjectMemberBuffer
 {
     fn new() -> Self
     {
-        Self { key: String::new(), value: String::new(), key_finished: false }
+        Self { key: String::new(), value: String::new(), key_finished: false, sort_key: None, placeholder: false }
     }
 
 
-    fn push(&mut self, ch: char)
+    fn push_str(&mut self, str: &str)
     {
         if self.key_finished
         {
-            self.value.push(ch);
+            self.value.push_str(str);
         }
         else
         {
-            self.key.push(ch);
+            self.key.push_str(str);
         }
     }
 
 
-    fn push_str(&mut self, str: &str)
+    fn len(&self) -> usize
     {
-        if self.key_finished
-        {
-            self.value.push_str(str);
-        }
-        else
-        {
-            self.key.push_str(str);
-        }
+        self.key.len() + self.value.len()
     }
 
 
@@ -139,19 +699,62 @@ impl ObjectMemberBuffer
     }
 
 
-    fn string(&self, first: bool) -> String
+    /// Undoes the escaping applied to the buffered key, which is stored quoted.
+    fn decoded_key(&self) -> String
     {
-        let prefix = if first
+        let escaped = self.key.strip_prefix('"').and_then(|key| key.strip_suffix('"')).unwrap_or(&self.key);
+
+        let mut decoded = String::with_capacity(escaped.len());
+        let mut chars = escaped.chars();
+
+        while let Some(ch) = chars.next()
         {
-            ""
+            if ch != '\\'
+            {
+                decoded.push(ch);
+                continue
+            }
+
+            match chars.next()
+            {
+                Some('b') => decoded.push('\x08'),
+                Some('f') => decoded.push('\x0C'),
+                Some('n') => decoded.push('\n'),
+                Some('r') => decoded.push('\r'),
+                Some('t') => decoded.push('\t'),
+                Some('u') =>
+                {
+                    fn unit(chars: &mut std::str::Chars) -> u32
+                    {
+                        u32::from_str_radix(&chars.take(4).collect::<String>(), 16).unwrap_or(0xFFFD)
+                    }
+
+                    let high = unit(&mut chars);
+                    let low = chars.as_str().strip_prefix("\\u")
+                        .and_then(|rest| rest.get(..4))
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .filter(|low| (0xDC00..0xE000).contains(low));
+
+                    let code_point = if let (true, Some(low)) = ((0xD800..0xDC00).contains(&high), low)
+                    {
+                        chars.nth(5);
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    else
+                    {
+                        high
+                    };
+
+                    decoded.push(char::from_u32(code_point).unwrap_or('\u{FFFD}'));
+                }
+                Some(other) => decoded.push(other),
+                None => (),
+            }
         }
-        else
-        {
-            ","
-        };
 
-        format!("{}{}:{}", prefix, &self.key, &self.value)
+        decoded
     }
+
 }
 
 
@@ -176,23 +779,312 @@ impl CanonicalFormatter
             return Err(Error::new(ErrorKind::InvalidData, "Object requested when object is not active."))
         };
 
-        let string = object.string();
+        if self.ijson.is_some()
+        {
+            if let Some(key) = object.duplicate_key()
+            {
+                return Err(CanonicalError::DuplicateKey(key).into())
+            }
+        }
 
-        // Check to see if this was the top of the stack
-        if let Some(parent) = self.current_object()
+        // An object that makes up a whole element of an array sorted by member leaves its sort key behind
+        let sort_key = match self.object_stack.front()
         {
-            let Some(member) = parent.current_member() else
+            Some(ObjectStackFrame { array: Some(ArraySort::ByMember(name)), members, .. }) if members.last().is_some_and(|element| element.value.is_empty()) =>
+            {
+                object.members.iter().find(|member| member.decoded_key() == *name).map(|member| member.value.clone())
+            }
+            _ => None,
+        };
+
+        self.buffered -= object.buffered_len();
+
+        let order = self.key_order.as_deref().map(|order| order as &dyn KeyOrder).or(self.profile.key_order());
+        let string = object.string(self.profile, order, self.depth)?;
+
+        // Either lands in the parent's current member, or this was the top of the stack
+        self.write_str(writer, &string)?;
+
+        if let Some(element) = self.current_object().and_then(ObjectStackFrame::current_member).filter(|_| sort_key.is_some())
+        {
+            element.sort_key = sort_key;
+        }
+
+        Ok(())
+    }
+
+
+    fn pop_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()>
+    {
+        let Some(mut array) = self.object_stack.pop_front() else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Array requested when array is not active."))
+        };
+
+        self.buffered -= array.buffered_len();
+
+        let string = array.array_string(self.profile, self.depth);
+
+        self.write_str(writer, &string)
+    }
+
+
+    /// A formatter with the same settings and nothing written yet, for output nested at the current depth.
+    #[cfg(feature = "nfc")]
+    fn nested(&self) -> Self
+    {
+        Self
+        {
+            profile: self.profile,
+            depth: self.depth,
+            ijson: self.ijson,
+            escape: self.escape,
+            normalization: self.normalization,
+            surrogates: self.surrogates,
+            key_order: self.key_order.clone(),
+            array_rules: self.array_rules.clone(),
+            exclusions: self.exclusions.clone(),
+            selections: self.selections.clone(),
+            elision: self.elision,
+            limits: self.limits,
+            path: self.path.clone(),
+            ..Self::default()
+        }
+    }
+
+
+    /// Applies the normalization policy to a fragment of a key or string.
+    ///
+    /// serde_json splits strings around escaped characters, but those never compose with their
+    /// neighbours, so normalizing each fragment on its own is the same as normalizing the whole.
+    fn normalize<'a>(&self, fragment: &'a str) -> io::Result<Cow<'a, str>>
+    {
+        #[cfg(feature = "nfc")]
+        {
+            use unicode_normalization::{UnicodeNormalization, is_nfc};
+
+            match self.normalization
+            {
+                Some(NormalizationPolicy::Normalize) if !is_nfc(fragment) => return Ok(fragment.nfc().collect::<String>().into()),
+                Some(NormalizationPolicy::Reject) if !is_nfc(fragment) => return Err(CanonicalError::NotNormalized.into()),
+                _ => (),
+            }
+        }
+
+        Ok(Cow::Borrowed(fragment))
+    }
+
+
+    /// Writes a 64-bit or wider integer, which Amino and I-JSON may quote unless it is already inside a map key.
+    fn write_wide_integer<W: ?Sized + io::Write>(&mut self, writer: &mut W, digits: &str, magnitude: u128) -> io::Result<()>
+    {
+        let in_key = self.current_object()
+            .and_then(ObjectStackFrame::current_member)
+            .is_some_and(|member| !member.key_finished);
+
+        let safe = magnitude <= ijson::MAX_SAFE_INTEGER;
+
+        if self.ijson == Some(IJsonPolicy::Strict) && !safe && !in_key
+        {
+            return Err(CanonicalError::UnsafeInteger(digits.to_owned()).into())
+        }
+
+        let quoted = (self.profile == Profile::Amino && !hint::in_untyped()) || (self.ijson == Some(IJsonPolicy::StringifyLargeIntegers) && !safe);
+
+        if quoted && !in_key
+        {
+            self.write_str(writer, &format!("\"{}\"", digits))
+        }
+        else
+        {
+            self.write_str(writer, digits)
+        }
+    }
+
+
+    /// Whether any rule needs the path to the value being written.
+    fn tracks_paths(&self) -> bool
+    {
+        !self.array_rules.is_empty() || !self.exclusions.is_empty() || !self.selections.is_empty()
+    }
+
+
+    /// Whether the selections keep `value`, which was just written at the current path.
+    ///
+    /// Containers on the way to a selected value are kept as long as something in them was.
+    fn selected(&self, value: &str) -> bool
+    {
+        self.selections.is_empty()
+            || self.selections.iter().any(|pointer| pointer.contains(&self.path))
+            || (self.selections.iter().any(|pointer| pointer.passes_through(&self.path)) && value.starts_with(['{', '[']) && !matches!(value, "{}" | "[]"))
+    }
+
+
+    pub(crate) fn surrogate_policy(&self) -> SurrogatePolicy
+    {
+        self.surrogates
+    }
+
+
+    /// Writes a lone surrogate kept by [SurrogatePolicy::Preserve], which I-JSON forbids.
+    pub(crate) fn write_surrogate_escape<W: ?Sized + io::Write>(&mut self, writer: &mut W, unit: u16) -> io::Result<()>
+    {
+        if self.ijson.is_some()
+        {
+            return Err(CanonicalError::LoneSurrogate(unit).into())
+        }
+
+        // Counted as the three bytes UTF-8 would need
+        self.count_string(3)?;
+        self.write_str(writer, &format!("\\u{:04x}", unit))
+    }
+
+
+    /// Streams objects whose keys are expected in order, for input that is known to be sorted already.
+    ///
+    /// Rules that drop or check members once the whole object is known keep buffering it.
+    pub(crate) fn with_sorted_input(mut self) -> Self
+    {
+        self.streaming = true;
+        self
+    }
+
+
+    /// Whether the next object can be written as it arrives.
+    fn streams_objects(&self) -> bool
+    {
+        self.streaming
+            && !self.tracks_paths()
+            && self.elision.is_none()
+            && !matches!(self.profile, Profile::JavaScript | Profile::DagJson | Profile::Ssb)
+            && self.object_stack.front().is_none_or(|object| object.streamed.is_some())
+    }
+
+
+    /// Writes the key of the current member of a streamed object, which must sort after the one before.
+    fn write_streamed_key<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()>
+    {
+        let order = self.key_order.clone();
+        let profile_order = self.profile.key_order();
+        let ijson = self.ijson.is_some();
+
+        let Some(object) = self.object_stack.front_mut() else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Object requested when object is not active."))
+        };
+        let (Some(member), Some(streamed)) = (object.members.pop(), object.streamed.as_mut()) else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
+        };
+
+        let key = member.decoded_key();
+
+        if let Some(previous) = &streamed.previous
+        {
+            let order = order.as_deref().map(|order| order as &dyn KeyOrder).or(profile_order);
+
+            match order.map(|order| order.compare(previous, &key))
+            {
+                Some(std::cmp::Ordering::Greater) => return Err(CanonicalError::UnsortedKey(key).into()),
+                Some(std::cmp::Ordering::Equal) | None if ijson && *previous == key => return Err(CanonicalError::DuplicateKey(key).into()),
+                _ => (),
+            }
+        }
+
+        let separator = if streamed.members > 0 { "," } else { "" };
+
+        streamed.previous = Some(key);
+        streamed.members += 1;
+        self.buffered -= member.len();
+
+        self.write_str(writer, &format!("{}{}:", separator, member.key))
+    }
+
+
+    /// Drops the member or element that was just written from the current object or array.
+    fn drop_member(&mut self)
+    {
+        if let Some(member) = self.current_object().and_then(|object| object.members.pop())
+        {
+            self.buffered -= member.len();
+        }
+    }
+
+
+    /// Leaves out the array element just written. Sorted arrays drop it, as their order does not come
+    /// from indices, and others write `null` in its place so the elements after it keep their index.
+    fn leave_out_element(&mut self)
+    {
+        let Some(array) = self.object_stack.front_mut() else
+        {
+            return
+        };
+
+        if array.array.is_some()
+        {
+            return self.drop_member()
+        }
+
+        if let Some(element) = array.members.last_mut()
+        {
+            self.buffered = self.buffered - element.value.len() + "null".len();
+            element.value = "null".to_owned();
+            element.placeholder = true;
+        }
+    }
+
+
+    /// Goes one object or array deeper, within the limit.
+    fn nest(&mut self) -> io::Result<()>
+    {
+        match self.limits.depth
+        {
+            Some(max) if self.depth >= max => Err(CanonicalError::TooDeep(max).into()),
+            _ =>
+            {
+                self.depth += 1;
+                Ok(())
+            }
+        }
+    }
+
+
+    /// Counts `len` more bytes of the current key or string against the limit.
+    fn count_string(&mut self, len: usize) -> io::Result<()>
+    {
+        self.string_length += len;
+
+        match self.limits.string_length
+        {
+            Some(max) if self.string_length > max => Err(CanonicalError::StringTooLong(max).into()),
+            _ => Ok(()),
+        }
+    }
+
+
+    fn write_str<W: ?Sized + io::Write>(&mut self, writer: &mut W, str: &str) -> io::Result<()>
+    {
+        let max_buffered = self.limits.buffered_bytes;
+
+        if let Some(object) = self.current_object().filter(|object| object.streamed.is_none() || !object.members.is_empty())
+        {
+            let Some(member) = object.current_member() else
             {
                 return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
             };
 
-            member.push_str(&string);
+            member.push_str(str);
+            self.buffered += str.len();
 
-            Ok(())
+            match max_buffered
+            {
+                Some(max) if self.buffered > max => Err(CanonicalError::TooLarge(max).into()),
+                _ => Ok(()),
+            }
         }
         else
         {
-            writer.write_all(string.as_bytes())
+            writer.write_all(str.as_bytes())
         }
     }
 }
@@ -200,19 +1092,35 @@ impl CanonicalFormatter
 
 impl Formatter for CanonicalFormatter
 {
-    fn write_f32<W>(&mut self, _writer: &mut W, _value: f32) -> io::Result<()>
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
         where
             W: ?Sized + io::Write,
     {
-        Err(Error::new(ErrorKind::InvalidData, "Floating point numbers are forbidden."))
+        match self.profile
+        {
+            // Debug formatting is the shortest round trip, and always has a decimal point or exponent
+            Profile::DagJson => self.write_str(writer, &format!("{:?}", value)),
+            // Python only has doubles
+            Profile::Python { .. } => self.write_str(writer, &float::python_repr(value as f64)),
+            Profile::JavaScript | Profile::Jcs | Profile::Ssb => self.write_str(writer, &float::ecmascript(value as f64)),
+            Profile::Go { .. } => self.write_str(writer, &float::go(&format!("{:e}", value))),
+            _ => Err(Error::new(ErrorKind::InvalidData, "Floating point numbers are forbidden.")),
+        }
     }
 
 
-    fn write_f64<W>(&mut self, _writer: &mut W, _value: f64) -> io::Result<()>
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
         where
             W: ?Sized + io::Write,
     {
-        Err(Error::new(ErrorKind::InvalidData, "Floating point numbers are forbidden."))
+        match self.profile
+        {
+            Profile::DagJson => self.write_str(writer, &format!("{:?}", value)),
+            Profile::Python { .. } => self.write_str(writer, &float::python_repr(value)),
+            Profile::JavaScript | Profile::Jcs | Profile::Ssb => self.write_str(writer, &float::ecmascript(value)),
+            Profile::Go { .. } => self.write_str(writer, &float::go(&format!("{:e}", value))),
+            _ => Err(Error::new(ErrorKind::InvalidData, "Floating point numbers are forbidden.")),
+        }
     }
 
 
@@ -226,26 +1134,39 @@ impl Formatter for CanonicalFormatter
         // - digit1-9
         // - digit1-9 digits
 
+        // DAG-JSON and Go also permit fractions and exponents, and Python, JavaScript and SSB reformat them as floats
+
         lazy_static!
         {
-            static ref RE: Regex = Regex::new(r"^\d$|^-[1-9]$|^-?[1-9]\d+$").unwrap();
+            static ref RE: Regex = Regex::new(r"^[0-9]$|^-[1-9]$|^-?[1-9][0-9]+$").unwrap();
+            static ref FLOAT_RE: Regex = Regex::new(r"^-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?$").unwrap();
         }
 
-        if RE.is_match(value)
+        if RE.is_match(value) && self.ijson.is_some()
+        {
+            // Digits beyond the range of u128 are certainly unsafe too
+            let magnitude = value.trim_start_matches('-').parse().unwrap_or(u128::MAX);
+
+            self.write_wide_integer(writer, value, magnitude)
+        }
+        else if RE.is_match(value) || (matches!(self.profile, Profile::DagJson | Profile::Go { .. }) && FLOAT_RE.is_match(value))
+        {
+            self.write_str(writer, value)
+        }
+        else if let (Profile::Python { .. } | Profile::JavaScript | Profile::Jcs | Profile::Ssb, true) = (self.profile, FLOAT_RE.is_match(value))
         {
-            if let Some(object) = self.current_object()
+            let Ok(value) = value.parse::<f64>() else
             {
-                let Some(member) = object.current_member() else
-                {
-                    return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-                };
-    
-                member.push_str(value);
-                Ok(())
+                return Err(Error::new(ErrorKind::InvalidData, "Number string in invalid format."))
+            };
+
+            if let Profile::JavaScript | Profile::Jcs | Profile::Ssb = self.profile
+            {
+                self.write_str(writer, &float::ecmascript(value))
             }
             else
             {
-                writer.write_all(value.as_bytes())
+                self.write_str(writer, &float::python_repr(value))
             }
         }
         else
@@ -261,58 +1182,59 @@ impl Formatter for CanonicalFormatter
     {
         use serde_json::ser::CharEscape::*;
 
+        self.count_string(1)?;
+
         // Only permitted escape values are for " and \
-        // Everything else passed through verbatim
+        // Everything else passed through verbatim, unless the profile or escape policy asks for short forms
+
+        let (short, escape_controls) = match self.escape
+        {
+            Some(policy) => (policy != EscapePolicy::Olpc, policy != EscapePolicy::Olpc),
+            None => (self.profile != Profile::Olpc, !matches!(self.profile, Profile::Olpc | Profile::Nostr)),
+        };
 
         let s = match char_escape {
             Quote => "\\\"",
             ReverseSolidus => "\\\\",
             Solidus => "/",
+            Backspace if short => "\\b",
+            FormFeed if short => "\\f",
+            LineFeed if short => "\\n",
+            CarriageReturn if short => "\\r",
+            Tab if short => "\\t",
             Backspace => "\x08",
             FormFeed => "\x0C",
             LineFeed => "\n",
             CarriageReturn => "\r",
             Tab => "\t",
+            AsciiControl(byte) if escape_controls =>
+            {
+                return self.write_str(writer, &format!("\\u{:04x}", byte))
+            }
             AsciiControl(byte) =>
             {
-                if let Some(object) = self.current_object()
-                {
-                    let Some(member) = object.current_member() else
-                    {
-                        return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-                    };
-        
-                    member.push(byte as char);
-                    return Ok(())
-                }
-                else
-                {
-                    return writer.write_all(&[byte])
-                }
+                return self.write_str(writer, (byte as char).encode_utf8(&mut [0; 4]))
             }
         };
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
 
-    fn begin_object<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
         where
             W: ?Sized + io::Write,
     {
+        self.nest()?;
+
+        if self.streams_objects()
+        {
+            self.write_str(writer, "{")?;
+            self.object_stack.push_front(ObjectStackFrame::new_streamed());
+
+            return Ok(())
+        }
+
         self.push_object();
 
         Ok(())
@@ -323,12 +1245,21 @@ impl Formatter for CanonicalFormatter
         where
             W: ?Sized + io::Write,
     {
+        let max_members = self.limits.members;
+
         let Some(object) = self.current_object() else
         {
             return Err(Error::new(ErrorKind::InvalidData, "Object key requested when object is not active."))
         };
 
-        object.push_member();
+        let members = object.streamed.as_ref().map_or(object.members.len(), |streamed| streamed.members);
+
+        match max_members
+        {
+            Some(max) if members >= max => return Err(CanonicalError::TooManyMembers(max).into()),
+            _ => object.push_member(),
+        }
+
         Ok(())
     }
 
@@ -337,20 +1268,8 @@ impl Formatter for CanonicalFormatter
         where
             W: ?Sized + io::Write,
     {
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str("\"");
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(b"\"")
-        }
+        self.string_length = 0;
+        self.write_str(writer, "\"")
     }
 
 
@@ -358,41 +1277,37 @@ impl Formatter for CanonicalFormatter
         where
             W: ?Sized + io::Write,
     {
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
+        self.count_string(fragment.len())?;
 
-            member.push_str(fragment);
-            Ok(())
-        }
-        else
+        let fragment = &*self.normalize(fragment)?;
+
+        if let (Some(ch), true) = (fragment.chars().find(|ch| ijson::is_noncharacter(*ch)), self.ijson.is_some())
         {
-            writer.write_all(fragment.as_bytes())
+            return Err(CanonicalError::Noncharacter(ch).into())
         }
-    }
 
+        let html = |ch: char| matches!(ch, '<' | '>' | '&' | '\u{2028}' | '\u{2029}');
 
-    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
-        where
-            W: ?Sized + io::Write,
-    {
-        if let Some(object) = self.current_object()
+        let escaped = match (self.escape, self.profile)
         {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
+            (Some(EscapePolicy::AsciiOnly), _) => escape_unicode(fragment, |ch| !ch.is_ascii()).into(),
+            (Some(EscapePolicy::HtmlSafe), _) => escape_unicode(fragment, html).into(),
+            (Some(_), _) => Cow::Borrowed(fragment),
+            (None, Profile::Python { ensure_ascii: true }) => escape_unicode(fragment, |ch| !(' '..='~').contains(&ch)).into(),
+            (None, Profile::Go { escape_html: true } | Profile::Amino) => escape_unicode(fragment, html).into(),
+            (None, Profile::Go { escape_html: false }) => escape_unicode(fragment, |ch| matches!(ch, '\u{2028}' | '\u{2029}')).into(),
+            (None, _) => Cow::Borrowed(fragment),
+        };
 
-            member.push_str("\"");
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(b"\"")
-        }
+        self.write_str(writer, &escaped)
+    }
+
+
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        self.write_str(writer, "\"")
     }
 
 
@@ -400,6 +1315,8 @@ impl Formatter for CanonicalFormatter
         where
             W: ?Sized + io::Write,
     {
+        let tracks_paths = self.tracks_paths();
+
         let Some(object) = self.current_object() else
         {
             return Err(Error::new(ErrorKind::InvalidData, "Object key requested when object is not active."))
@@ -410,14 +1327,26 @@ impl Formatter for CanonicalFormatter
         };
 
         member.finish_key();
+
+        if tracks_paths
+        {
+            let key = member.decoded_key();
+            self.path.push(Segment::Key(key));
+        }
+
         Ok(())
     }
 
 
-    fn begin_object_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
         where
             W: ?Sized + io::Write,
     {
+        if self.object_stack.front().is_some_and(|object| object.streamed.is_some())
+        {
+            return self.write_streamed_key(writer)
+        }
+
         Ok(())
     }
 
@@ -426,6 +1355,38 @@ impl Formatter for CanonicalFormatter
         where
             W: ?Sized + io::Write,
     {
+        let tracks_paths = self.tracks_paths();
+
+        if !tracks_paths && self.elision.is_none()
+        {
+            return Ok(())
+        }
+
+        let Some(value) = self.object_stack.front().and_then(|object| object.members.last()).map(|member| &*member.value) else
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
+        };
+
+        // An object that loses all of its members is written as {}, so elision cascades up to its parent
+        let elided = match self.elision
+        {
+            Some(ElisionPolicy::Null) => value == "null",
+            Some(ElisionPolicy::NullAndEmpty) => matches!(value, "null" | "[]" | "{}"),
+            None => false,
+        };
+
+        let dropped = elided || (tracks_paths && (!self.selected(value) || self.exclusions.iter().any(|pointer| pointer.matches(&self.path))));
+
+        if dropped
+        {
+            self.drop_member();
+        }
+
+        if tracks_paths
+        {
+            self.path.pop();
+        }
+
         Ok(())
     }
 
@@ -434,6 +1395,14 @@ impl Formatter for CanonicalFormatter
         where
             W: ?Sized + io::Write,
     {
+        self.depth = self.depth.saturating_sub(1);
+
+        if self.object_stack.front().is_some_and(|object| object.streamed.is_some())
+        {
+            self.object_stack.pop_front();
+            return self.write_str(writer, "}")
+        }
+
         self.pop_object(writer)
     }
     
@@ -443,20 +1412,12 @@ impl Formatter for CanonicalFormatter
     where
         W: ?Sized + io::Write,
     {
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str("null");
-            Ok(())
-        }
-        else
+        if hint::take_non_finite()
         {
-            writer.write_all(b"null")
+            return Err(CanonicalError::NonFiniteFloat.into());
         }
+
+        self.write_str(writer, "null")
     }
 
     /// Writes a `true` or `false` value to the specified writer.
@@ -471,20 +1432,7 @@ impl Formatter for CanonicalFormatter
             "false"
         };
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
     /// Writes an integer value like `-123` to the specified writer.
@@ -496,20 +1444,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
     /// Writes an integer value like `-123` to the specified writer.
@@ -521,20 +1456,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
     /// Writes an integer value like `-123` to the specified writer.
@@ -546,20 +1468,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
     /// Writes an integer value like `-123` to the specified writer.
@@ -571,20 +1480,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_wide_integer(writer, s, value.unsigned_abs() as u128)
     }
 
     /// Writes an integer value like `-123` to the specified writer.
@@ -596,20 +1492,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_wide_integer(writer, s, value.unsigned_abs())
     }
 
     /// Writes an integer value like `123` to the specified writer.
@@ -621,20 +1504,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
     /// Writes an integer value like `123` to the specified writer.
@@ -646,20 +1516,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
     /// Writes an integer value like `123` to the specified writer.
@@ -671,20 +1528,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_str(writer, s)
     }
 
     /// Writes an integer value like `123` to the specified writer.
@@ -696,20 +1540,7 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
-        {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
-
-            member.push_str(s);
-            Ok(())
-        }
-        else
-        {
-            writer.write_all(s.as_bytes())
-        }
+        self.write_wide_integer(writer, s, value as u128)
     }
 
     /// Writes an integer value like `123` to the specified writer.
@@ -721,20 +1552,33 @@ impl Formatter for CanonicalFormatter
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
 
-        if let Some(object) = self.current_object()
+        self.write_wide_integer(writer, s, value)
+    }
+
+    /// Writes the representation of a byte array, which is a `{"/":{"bytes":"..."}}`
+    /// object under DAG-JSON, a base64 string under Go, and an array of integers otherwise.
+    fn write_byte_array<W>(&mut self, writer: &mut W, value: &[u8]) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if self.profile == Profile::DagJson
         {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
+            return self.write_str(writer, &format!("{{\"/\":{{\"bytes\":\"{}\"}}}}", base64(value, false)))
+        }
 
-            member.push_str(s);
-            Ok(())
+        if let Profile::Go { .. } | Profile::Amino = self.profile
+        {
+            return self.write_str(writer, &format!("\"{}\"", base64(value, true)))
         }
-        else
+
+        self.begin_array(writer)?;
+        for (index, byte) in value.iter().enumerate()
         {
-            writer.write_all(s.as_bytes())
+            self.begin_array_value(writer, index == 0)?;
+            self.write_u8(writer, *byte)?;
+            self.end_array_value(writer)?;
         }
+        self.end_array(writer)
     }
 
     /// Called before every array.  Writes a `[` to the specified
@@ -744,42 +1588,61 @@ impl Formatter for CanonicalFormatter
     where
         W: ?Sized + io::Write,
     {
-        if let Some(object) = self.current_object()
+        self.nest()?;
+        self.has_value = false;
+
+        let set = hint::take_set().map(|dedup| ArraySort::CanonicalBytes { dedup });
+
+        let sort = self.array_rules.iter().find(|(pointer, _)| pointer.matches(&self.path)).map(|(_, sort)| sort.clone()).or(set);
+
+        // Arrays that selections pass through may lose elements, so they are buffered too
+        let buffered = sort.is_some() || !(self.selections.is_empty() || self.selections.iter().any(|pointer| pointer.contains(&self.path)));
+
+        if self.tracks_paths()
         {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
+            self.path.push(Segment::Index(0));
+        }
+
+        self.buffered_arrays.push(buffered);
 
-            member.push_str("[");
+        if buffered
+        {
+            // Elements are buffered like object members, and the brackets written when the array is popped
+            self.object_stack.push_front(ObjectStackFrame::new_array(sort));
             Ok(())
         }
         else
         {
-            writer.write_all(b"[")
+            self.write_str(writer, "[")
         }
     }
 
     /// Called after every array.  Writes a `]` to the specified
-    /// writer.
+    /// writer, on its own line if SSB indentation applies.
     #[inline]
     fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
         W: ?Sized + io::Write,
     {
-        if let Some(object) = self.current_object()
+        self.depth = self.depth.saturating_sub(1);
+
+        if self.tracks_paths()
         {
-            let Some(member) = object.current_member() else
-            {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
-            };
+            self.path.pop();
+        }
 
-            member.push_str("]");
-            Ok(())
+        if self.buffered_arrays.pop() == Some(true)
+        {
+            return self.pop_array(writer)
+        }
+
+        if self.profile == Profile::Ssb && self.has_value
+        {
+            self.write_str(writer, &format!("\n{}]", "  ".repeat(self.depth)))
         }
         else
         {
-            writer.write_all(b"]")
+            self.write_str(writer, "]")
         }
     }
 
@@ -790,24 +1653,35 @@ impl Formatter for CanonicalFormatter
     where
         W: ?Sized + io::Write,
     {
-        if first
+        if let (Some(Segment::Index(index)), false) = (self.path.last_mut(), first)
         {
-            Ok(())
+            *index += 1;
         }
-        else if let Some(object) = self.current_object()
+
+        if self.buffered_arrays.last() == Some(&true)
         {
-            let Some(member) = object.current_member() else
+            let Some(frame) = self.current_object() else
             {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
+                return Err(Error::new(ErrorKind::InvalidData, "Array requested when array is not active."))
             };
 
-            member.push_str(",");
-            Ok(())
+            frame.push_member();
+            frame.current_member().map(ObjectMemberBuffer::finish_key);
+
+            return Ok(())
         }
-        else
+
+        if !first
+        {
+            self.write_str(writer, ",")?;
+        }
+
+        if self.profile == Profile::Ssb
         {
-            writer.write_all(b",")
+            self.write_str(writer, &format!("\n{}", "  ".repeat(self.depth)))?;
         }
+
+        Ok(())
     }
 
     /// Called after every array value.
@@ -816,6 +1690,22 @@ impl Formatter for CanonicalFormatter
     where
         W: ?Sized + io::Write,
     {
+        self.has_value = true;
+
+        if self.buffered_arrays.last() != Some(&true)
+        {
+            return Ok(())
+        }
+
+        let selected = self.object_stack.front()
+            .and_then(|array| array.members.last())
+            .is_some_and(|element| self.selected(&element.value));
+
+        if !selected
+        {
+            self.leave_out_element();
+        }
+
         Ok(())
     }
 
@@ -826,23 +1716,94 @@ impl Formatter for CanonicalFormatter
     where
         W: ?Sized + io::Write,
     {
-        if let Some(object) = self.current_object()
+        if self.ijson.is_some()
         {
-            let Some(member) = object.current_member() else
+            ijson::check_raw_fragment(fragment)?;
+        }
+
+        #[cfg(feature = "nfc")]
+        if self.normalization.is_some()
+        {
+            // Written again through this formatter, so its strings are normalized and its keys sorted
+            let value: serde_json::Value = serde_json::from_str(fragment)?;
+
+            let mut ser = serde_json::Serializer::with_formatter(Vec::new(), self.nested());
+            serde::Serialize::serialize(&value, &mut ser)?;
+
+            let Ok(fragment) = String::from_utf8(ser.into_inner()) else
             {
-                return Err(Error::new(ErrorKind::InvalidData, "Object member requested when member is not active."))
+                return Err(Error::new(ErrorKind::InvalidData, "Raw fragment is not valid UTF-8."))
             };
 
-            member.push_str(fragment);
-            Ok(())
+            return self.write_str(writer, &fragment)
+        }
+
+        self.write_str(writer, fragment)
+    }
+}
+
+
+/// Compares two JSON values as written, numerically if both are numbers.
+fn compare_values(a: &str, b: &str) -> std::cmp::Ordering
+{
+    // Strings, the only other values that could parse, start with a quote
+    match (a.parse::<f64>(), b.parse::<f64>())
+    {
+        (Ok(a_number), Ok(b_number)) => a_number.total_cmp(&b_number).then_with(|| a.cmp(b)),
+        _ => a.cmp(b),
+    }
+}
+
+
+/// Writes the characters of `fragment` chosen by `escape` as `\uxxxx`, using surrogate pairs where needed.
+fn escape_unicode(fragment: &str, escape: impl Fn(char) -> bool) -> String
+{
+    let mut escaped = String::with_capacity(fragment.len());
+
+    for ch in fragment.chars()
+    {
+        if escape(ch)
+        {
+            for unit in ch.encode_utf16(&mut [0; 2])
+            {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
         }
         else
         {
-            writer.write_all(fragment.as_bytes())
+            escaped.push(ch);
         }
     }
+
+    escaped
+}
+
+
+/// Standard base64, unpadded as DAG-JSON uses for bytes or padded as Go does.
+fn base64(bytes: &[u8], pad: bool) -> String
+{
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3)
+    {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, byte)| bits | (*byte as u32) << (16 - 8 * index));
+
+        for index in 0..=chunk.len()
+        {
+            output.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3F) as usize] as char);
+        }
+
+        if pad
+        {
+            output.extend(std::iter::repeat_n('=', 3 - chunk.len()));
+        }
+    }
+
+    output
 }
 
 
 #[cfg(test)]
-mod tests;
\ No newline at end of file
+mod tests;
@@ -0,0 +1,149 @@
+//! # Nostr events
+//!
+//! Computes [NIP-01](https://github.com/nostr-protocol/nips/blob/master/01.md) event ids and signs and
+//! verifies events with BIP-340 Schnorr signatures.
+//!
+//! The event id is the SHA-256 of the array `[0,pubkey,created_at,kind,tags,content]`, serialized with
+//! [Profile::Nostr] so that the escaping matches every other implementation.
+//!
+//! ```rust
+//! use k256::schnorr::SigningKey;
+//! use serde_canonical_json::nostr::{self, UnsignedEvent};
+//!
+//! let key = SigningKey::from_bytes(&[3; 32]).unwrap();
+//!
+//! let event = UnsignedEvent { created_at: 1700000000, kind: 1, tags: vec![], content: "Hello\nnostr".to_owned() };
+//! let event = nostr::sign_event(&event, &key).unwrap();
+//!
+//! nostr::verify_event(&event).unwrap();
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use crate::{CanonicalFormatter, Profile, to_vec_with_formatter};
+
+
+/// A signed Nostr event.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Event
+{
+    pub id: String,
+    pub pubkey: String,
+    pub created_at: u64,
+    pub kind: u16,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+    pub sig: String,
+}
+
+
+/// The members of an event chosen by its author, before the id and signature are computed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UnsignedEvent
+{
+    pub created_at: u64,
+    pub kind: u16,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+}
+
+
+/// Serializes the members of an event into the array that its id is computed from.
+pub fn serialize_event(pubkey: &str, created_at: u64, kind: u16, tags: &[Vec<String>], content: &str) -> io::Result<Vec<u8>>
+{
+    to_vec_with_formatter(&(0, pubkey, created_at, kind, tags, content), CanonicalFormatter::with_profile(Profile::Nostr))
+}
+
+
+/// Computes the id of `event` from its members, ignoring the `id` and `sig` it carries.
+pub fn event_id(event: &Event) -> io::Result<String>
+{
+    Ok(hex::encode(id_hash(&event.pubkey, event.created_at, event.kind, &event.tags, &event.content)?))
+}
+
+
+/// Signs `event` with the given key.
+///
+/// Uses all-zero auxiliary randomness, which BIP-340 permits; see [sign_event_with_aux_rand] to supply
+/// fresh randomness.
+pub fn sign_event(event: &UnsignedEvent, key: &SigningKey) -> io::Result<Event>
+{
+    sign_event_with_aux_rand(event, key, &[0; 32])
+}
+
+
+/// Signs `event` with the given key and BIP-340 auxiliary randomness.
+pub fn sign_event_with_aux_rand(event: &UnsignedEvent, key: &SigningKey, aux_rand: &[u8; 32]) -> io::Result<Event>
+{
+    let pubkey = hex::encode(key.verifying_key().to_bytes());
+    let id = id_hash(&pubkey, event.created_at, event.kind, &event.tags, &event.content)?;
+
+    let Ok(sig) = key.sign_prehash_with_aux_rand(&id, aux_rand) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Event could not be signed."))
+    };
+
+    Ok(Event
+    {
+        id: hex::encode(id),
+        pubkey,
+        created_at: event.created_at,
+        kind: event.kind,
+        tags: event.tags.clone(),
+        content: event.content.clone(),
+        sig: hex::encode(sig.to_bytes()),
+    })
+}
+
+
+/// Checks that the id of `event` matches its members and that `sig` is a valid signature by `pubkey`.
+pub fn verify_event(event: &Event) -> io::Result<()>
+{
+    let id = id_hash(&event.pubkey, event.created_at, event.kind, &event.tags, &event.content)?;
+
+    if hex::encode(id) != event.id
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Event id does not match its contents."))
+    }
+
+    let Ok(key) = decode_hex(&event.pubkey).and_then(|bytes| VerifyingKey::from_bytes(&bytes).map_err(|_| ())) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Event pubkey is not a valid x-only public key."))
+    };
+
+    let Ok(sig) = decode_hex(&event.sig).and_then(|bytes| Signature::try_from(bytes.as_slice()).map_err(|_| ())) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Event sig is not a valid Schnorr signature."))
+    };
+
+    if key.verify_raw(&id, &sig).is_err()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Event signature is invalid."))
+    }
+
+    Ok(())
+}
+
+
+fn id_hash(pubkey: &str, created_at: u64, kind: u16, tags: &[Vec<String>], content: &str) -> io::Result<[u8; 32]>
+{
+    Ok(Sha256::digest(serialize_event(pubkey, created_at, kind, tags, content)?).into())
+}
+
+
+fn decode_hex(encoded: &str) -> Result<Vec<u8>, ()>
+{
+    // NIP-01 requires lowercase hex
+    if encoded.bytes().any(|byte| byte.is_ascii_uppercase())
+    {
+        return Err(())
+    }
+
+    hex::decode(encoded).map_err(|_| ())
+}
+
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,79 @@
+use k256::schnorr::SigningKey;
+use crate::nostr::{self, Event, UnsignedEvent};
+
+
+// The reference event was produced with the BIP-340 reference implementation and a hand-escaped
+// NIP-01 serialization, using secret key 3 and all-zero auxiliary randomness
+
+const PUBKEY: &str = "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+const ID: &str = "5f0b2cd1a7365155a4f4cd656b0f24cb8f767281801cd54ee20eac4182e4803a";
+const SIG: &str = "9d1b4ffc332dc516435eb1b9bf31ae494ad098cb226e6bc5617e14083fabcc205cedea5ac004d779cebb0ea5f4df1074778e6377631a3b624991ffe1ce5842b3";
+const CONTENT: &str = "Hello \"nostr\"\n\tback\\slash\x01 héllo 🌍";
+
+
+fn tags() -> Vec<Vec<String>>
+{
+    vec![
+        vec!["e".to_owned(), "5c83da77af1dec6d7289834998ad7aafbd9e2191396d75ec3cc27f5a77226f36".to_owned(), "wss://relay.example".to_owned()],
+        vec!["p".to_owned(), PUBKEY.to_owned()],
+    ]
+}
+
+
+fn reference_event() -> Event
+{
+    Event
+    {
+        id: ID.to_owned(),
+        pubkey: PUBKEY.to_owned(),
+        created_at: 1700000000,
+        kind: 1,
+        tags: tags(),
+        content: CONTENT.to_owned(),
+        sig: SIG.to_owned(),
+    }
+}
+
+
+#[test]
+fn serialization()
+{
+    let serialized = nostr::serialize_event(PUBKEY, 1700000000, 1, &tags(), CONTENT).unwrap();
+
+    let expected = format!(
+        r#"[0,"{0}",1700000000,1,[["e","5c83da77af1dec6d7289834998ad7aafbd9e2191396d75ec3cc27f5a77226f36","wss://relay.example"],["p","{0}"]],"Hello \"nostr\"\n\tback\\slash{1} héllo 🌍"]"#,
+        PUBKEY,
+        '\x01',
+    );
+
+    assert_eq!(String::from_utf8(serialized).unwrap(), expected);
+    assert_eq!(nostr::event_id(&reference_event()).unwrap(), ID);
+}
+
+
+#[test]
+fn sign_reference_event()
+{
+    let mut secret = [0; 32];
+    secret[31] = 3;
+    let key = SigningKey::from_bytes(&secret).unwrap();
+
+    let unsigned = UnsignedEvent { created_at: 1700000000, kind: 1, tags: tags(), content: CONTENT.to_owned() };
+
+    assert_eq!(nostr::sign_event(&unsigned, &key).unwrap(), reference_event());
+}
+
+
+#[test]
+fn verify_reference_event()
+{
+    nostr::verify_event(&reference_event()).unwrap();
+
+    let mut tampered = reference_event();
+    tampered.content.push('!');
+    assert!(nostr::verify_event(&tampered).is_err());
+
+    let mut tampered = reference_event();
+    tampered.sig.replace_range(..2, "00");
+    assert!(nostr::verify_event(&tampered).is_err());
+}
@@ -0,0 +1,169 @@
+//! Orders in which a [CanonicalFormatter](crate::CanonicalFormatter) can sort object members.
+
+use std::cmp::Ordering;
+
+
+/// Compares two object keys, after their escapes have been undone.
+///
+/// Members whose keys compare equal keep the order they were serialized in.
+pub trait KeyOrder
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering;
+}
+
+
+impl<F> KeyOrder for F
+    where
+        F: Fn(&str, &str) -> Ordering,
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering
+    {
+        self(a, b)
+    }
+}
+
+
+/// Sorts keys by their UTF-8 bytes, as Go and Python do.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Utf8Bytes;
+
+
+impl KeyOrder for Utf8Bytes
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering
+    {
+        a.as_bytes().cmp(b.as_bytes())
+    }
+}
+
+
+/// Sorts keys by their Unicode code points, which is the same order as [Utf8Bytes].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CodePoints;
+
+
+impl KeyOrder for CodePoints
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering
+    {
+        a.chars().cmp(b.chars())
+    }
+}
+
+
+/// Sorts keys by their UTF-16 code units, as JavaScript and RFC 8785 do.
+///
+/// This only differs from [CodePoints] above U+FFFF, which sorts before U+E000 to U+FFFF.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Utf16Units;
+
+
+impl KeyOrder for Utf16Units
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering
+    {
+        a.encode_utf16().cmp(b.encode_utf16())
+    }
+}
+
+
+/// Sorts shorter keys first and keys of the same length by their UTF-8 bytes, as DAG-JSON and CBOR do.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LengthThenBytes;
+
+
+impl KeyOrder for LengthThenBytes
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering
+    {
+        a.len().cmp(&b.len()).then_with(|| a.as_bytes().cmp(b.as_bytes()))
+    }
+}
+
+
+/// Sorts integer keys, such as `"-3"` or `"10"`, numerically and before every other key, which are
+/// sorted by their UTF-8 bytes.
+///
+/// Only the shortest form of an integer counts, so `"007"`, `"+1"` and `"-0"` are ordinary keys.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NumericAware;
+
+
+impl NumericAware
+{
+    /// Splits an integer key into its sign and digits.
+    fn integer(key: &str) -> Option<(bool, &str)>
+    {
+        let (negative, digits) = match key.strip_prefix('-')
+        {
+            Some(digits) => (true, digits),
+            None => (false, key),
+        };
+
+        let shortest = match digits.as_bytes()
+        {
+            [] => false,
+            [b'0'] => !negative,
+            [first, ..] => *first != b'0',
+        };
+
+        (shortest && digits.bytes().all(|byte| byte.is_ascii_digit())).then_some((negative, digits))
+    }
+}
+
+
+impl KeyOrder for NumericAware
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering
+    {
+        match (Self::integer(a), Self::integer(b))
+        {
+            (Some((a_negative, a_digits)), Some((b_negative, b_digits))) =>
+            {
+                let magnitude = a_digits.len().cmp(&b_digits.len()).then_with(|| a_digits.cmp(b_digits));
+
+                match (a_negative, b_negative)
+                {
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (true, true) => magnitude.reverse(),
+                    (false, false) => magnitude,
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.as_bytes().cmp(b.as_bytes()),
+        }
+    }
+}
+
+
+/// The OLPC order, which compares keys as they are written with only `"` and `\` escaped.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct OlpcEscaped;
+
+
+impl KeyOrder for OlpcEscaped
+{
+    fn compare(&self, a: &str, b: &str) -> Ordering
+    {
+        fn escaped(key: &str) -> impl Iterator<Item = u8> + '_
+        {
+            key.bytes()
+                .flat_map(|byte| match byte
+                {
+                    b'"' | b'\\' => [Some(b'\\'), Some(byte)],
+                    _ => [Some(byte), None],
+                })
+                .flatten()
+                .chain(std::iter::once(b'"'))
+        }
+
+        // The closing quote takes part, as it did when the quoted keys were compared
+        escaped(a).cmp(escaped(b))
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+use std::cmp::Ordering;
+use serde_json::json;
+use crate::{CanonicalFormatter, CodePoints, KeyOrder, LengthThenBytes, NumericAware, Profile, Utf16Units, Utf8Bytes};
+
+
+fn sorted(order: &dyn KeyOrder, keys: &[&str]) -> Vec<String>
+{
+    let mut keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+    keys.sort_by(|a, b| order.compare(a, b));
+
+    keys
+}
+
+
+fn serialize(formatter: CanonicalFormatter, value: &serde_json::Value) -> String
+{
+    let mut ser = serde_json::Serializer::with_formatter(Vec::new(), formatter);
+    serde::Serialize::serialize(value, &mut ser).unwrap();
+
+    String::from_utf8(ser.into_inner()).unwrap()
+}
+
+
+#[test]
+fn built_in_orders()
+{
+    let keys = ["b", "\u{FB01}", "aa", "\u{1F600}", "a", "10", "9", "-2"];
+
+    assert_eq!(sorted(&Utf8Bytes, &keys), ["-2", "10", "9", "a", "aa", "b", "\u{FB01}", "\u{1F600}"]);
+    assert_eq!(sorted(&CodePoints, &keys), sorted(&Utf8Bytes, &keys));
+    assert_eq!(sorted(&Utf16Units, &keys), ["-2", "10", "9", "a", "aa", "b", "\u{1F600}", "\u{FB01}"]);
+    assert_eq!(sorted(&LengthThenBytes, &keys), ["9", "a", "b", "-2", "10", "aa", "\u{FB01}", "\u{1F600}"]);
+}
+
+
+#[test]
+fn numeric_aware()
+{
+    let keys = ["b", "10", "-0", "9", "007", "-10", "0", "-9", "123456789012345678901234567890", "a"];
+
+    assert_eq!(sorted(&NumericAware, &keys), ["-10", "-9", "0", "9", "10", "123456789012345678901234567890", "-0", "007", "a", "b"]);
+}
+
+
+#[test]
+fn formatter_uses_key_order()
+{
+    let value = json!({ "10": { "b": 1, "a": 2 }, "9": [{ "2": null, "1": null }], "x": 0 });
+
+    assert_eq!(serialize(CanonicalFormatter::new(), &value), r#"{"10":{"a":2,"b":1},"9":[{"1":null,"2":null}],"x":0}"#);
+    assert_eq!(serialize(CanonicalFormatter::new().with_key_order(NumericAware), &value), r#"{"9":[{"1":null,"2":null}],"10":{"a":2,"b":1},"x":0}"#);
+
+    // Orders apply to the decoded key, and override the profile's own
+    let escaped = json!({ "\u{1F600}": 1, "\u{FB01}": 2, "\n": 3 });
+    let formatter = CanonicalFormatter::with_profile(Profile::Python { ensure_ascii: true }).with_key_order(Utf16Units);
+
+    assert_eq!(serialize(formatter, &escaped), r#"{"\n":3,"\ud83d\ude00":1,"\ufb01":2}"#);
+
+    let reversed = |a: &str, b: &str| b.cmp(a);
+    assert_eq!(serialize(CanonicalFormatter::with_profile(Profile::Ssb).with_key_order(reversed), &json!({ "a": 1, "b": 2 })), "{\n  \"b\": 2,\n  \"a\": 1\n}");
+    assert_eq!(reversed.compare("a", "b"), Ordering::Greater);
+}
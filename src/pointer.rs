@@ -0,0 +1,85 @@
+//! JSON pointer patterns, and the paths they are matched against while serializing.
+
+use std::io::{self, ErrorKind, Error};
+
+
+/// A step from a container to one of its values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Segment
+{
+    Key(String),
+    Index(usize),
+}
+
+
+/// A [JSON pointer](https://www.rfc-editor.org/rfc/rfc6901) where a `*` segment matches any key or index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Pointer
+{
+    tokens: Vec<Option<String>>,
+}
+
+
+impl Pointer
+{
+    /// Parses a pointer such as `/components/*/purl`, where `~1` stands for `/` and `~0` for `~`.
+    pub(crate) fn parse(pointer: &str) -> io::Result<Self>
+    {
+        if pointer.is_empty()
+        {
+            return Ok(Self { tokens: Vec::new() })
+        }
+
+        let Some(rest) = pointer.strip_prefix('/') else
+        {
+            return Err(Error::new(ErrorKind::InvalidInput, "JSON pointer must be empty or start with '/'."))
+        };
+
+        if rest.split('~').skip(1).any(|escaped| !escaped.starts_with(['0', '1']))
+        {
+            return Err(Error::new(ErrorKind::InvalidInput, "JSON pointer has a '~' that is not followed by '0' or '1'."))
+        }
+
+        let tokens = rest.split('/')
+            .map(|token| (token != "*").then(|| token.replace("~1", "/").replace("~0", "~")))
+            .collect();
+
+        Ok(Self { tokens })
+    }
+
+
+    /// Whether the pointer points at `path` itself.
+    pub(crate) fn matches(&self, path: &[Segment]) -> bool
+    {
+        self.tokens.len() == path.len() && self.matches_start(path)
+    }
+
+
+    /// Whether `path` leads to the value the pointer points at, or to one inside it.
+    pub(crate) fn contains(&self, path: &[Segment]) -> bool
+    {
+        self.tokens.len() <= path.len() && self.matches_start(path)
+    }
+
+
+    /// Whether `path` leads to a container on the way to the value the pointer points at.
+    pub(crate) fn passes_through(&self, path: &[Segment]) -> bool
+    {
+        self.tokens.len() > path.len() && self.matches_start(path)
+    }
+
+
+    fn matches_start(&self, path: &[Segment]) -> bool
+    {
+        self.tokens.iter().zip(path).all(|(token, segment)| match (token, segment)
+        {
+            (None, _) => true,
+            (Some(token), Segment::Key(key)) => token == key,
+            (Some(token), Segment::Index(index)) => *token == index.to_string(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
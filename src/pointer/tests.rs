@@ -0,0 +1,37 @@
+use crate::pointer::{Pointer, Segment};
+
+
+fn path(segments: &[&str]) -> Vec<Segment>
+{
+    segments.iter()
+        .map(|segment| match segment.parse()
+        {
+            Ok(index) => Segment::Index(index),
+            Err(_) => Segment::Key(segment.to_string()),
+        })
+        .collect()
+}
+
+
+#[test]
+fn matches()
+{
+    assert!(Pointer::parse("").unwrap().matches(&[]));
+    assert!(Pointer::parse("/a/0").unwrap().matches(&path(&["a", "0"])));
+    assert!(Pointer::parse("/a/*/b").unwrap().matches(&path(&["a", "7", "b"])));
+    assert!(Pointer::parse("/a~1b/~0").unwrap().matches(&path(&["a/b", "~"])));
+    assert!(Pointer::parse("/").unwrap().matches(&[Segment::Key(String::new())]));
+
+    assert!(!Pointer::parse("/a").unwrap().matches(&path(&["a", "b"])));
+    assert!(!Pointer::parse("/a/*").unwrap().matches(&path(&["a"])));
+    assert!(!Pointer::parse("/a/01").unwrap().matches(&path(&["a", "1"])));
+}
+
+
+#[test]
+fn invalid_pointers()
+{
+    assert!(Pointer::parse("a/b").is_err());
+    assert!(Pointer::parse("/a~2b").is_err());
+    assert!(Pointer::parse("/a~").is_err());
+}
@@ -0,0 +1,76 @@
+//! # Projections
+//!
+//! Canonical bytes of selected parts of a document, for signatures that only cover those parts.
+//!
+//! A projection keeps the values at a set of JSON pointers along with the objects and arrays that lead
+//! to them, so the signed bytes still show where each value came from. See
+//! [CanonicalFormatter::with_selection] for how pointers select values.
+//!
+//! ```rust
+//! use serde_json::json;
+//! use serde_canonical_json::{CanonicalFormatter, projection};
+//!
+//! let statement = json!({
+//!     "subject": [{ "name": "app", "digest": { "sha256": "abc" } }],
+//!     "predicate": { "builder": { "id": "ci" }, "materials": [{ "uri": "git+https://example.com" }] },
+//! });
+//!
+//! let pointers = ["/subject", "/predicate/materials"];
+//! let bytes = projection::to_vec(&statement, &pointers, CanonicalFormatter::new()).unwrap();
+//!
+//! assert_eq!(bytes, br#"{"predicate":{"materials":[{"uri":"git+https://example.com"}]},"subject":[{"digest":{"sha256":"abc"},"name":"app"}]}"#);
+//!
+//! // The builder is not covered, so changing it keeps the signature valid
+//! let mut changed = statement.clone();
+//! changed["predicate"]["builder"]["id"] = json!("laptop");
+//!
+//! projection::verify(&changed, &pointers, CanonicalFormatter::new(), |signed| signed == bytes).unwrap();
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use serde::Serialize;
+use crate::CanonicalFormatter;
+
+
+/// Serializes the parts of `value` at `pointers` with `formatter`.
+///
+/// # Errors
+///
+/// If a pointer is neither empty nor starts with `/`, or has a `~` that is not `~0` or `~1`.
+pub fn to_vec<T>(value: &T, pointers: &[&str], formatter: CanonicalFormatter) -> io::Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+{
+    let formatter = pointers.iter().try_fold(formatter, |formatter, pointer| formatter.with_selection(pointer))?;
+
+    let mut ser = serde_json::Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser)?;
+
+    Ok(ser.into_inner())
+}
+
+
+/// Checks a signature over the parts of `value` at `pointers`, by handing their canonical bytes to
+/// `verify_signature`, which returns whether the signature is valid for them.
+///
+/// # Errors
+///
+/// If a pointer is neither empty nor starts with `/`, or has a `~` that is not `~0` or `~1`.
+pub fn verify<T, F>(value: &T, pointers: &[&str], formatter: CanonicalFormatter, verify_signature: F) -> io::Result<()>
+    where
+        T: ?Sized + Serialize,
+        F: FnOnce(&[u8]) -> bool,
+{
+    let bytes = to_vec(value, pointers, formatter)?;
+
+    if !verify_signature(&bytes)
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Projection signature is invalid."))
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests;
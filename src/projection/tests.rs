@@ -0,0 +1,108 @@
+use serde::Serialize;
+use serde_json::json;
+use crate::{CanonicalFormatter, projection};
+
+
+fn project<T: Serialize>(value: &T, pointers: &[&str]) -> String
+{
+    String::from_utf8(projection::to_vec(value, pointers, CanonicalFormatter::new()).unwrap()).unwrap()
+}
+
+
+#[test]
+fn selects_subtrees()
+{
+    let document = json!({
+        "a": { "b": 1, "c": [10, 20, { "d": 2, "e": 3 }] },
+        "f": 4,
+        "g": { "h": 5 },
+        "empty": {},
+    });
+
+    assert_eq!(project(&document, &[""]), r#"{"a":{"b":1,"c":[10,20,{"d":2,"e":3}]},"empty":{},"f":4,"g":{"h":5}}"#);
+    assert_eq!(project(&document, &["/a/b", "/f"]), r#"{"a":{"b":1},"f":4}"#);
+    assert_eq!(project(&document, &["/a/c/2/e"]), r#"{"a":{"c":[null,null,{"e":3}]}}"#);
+    assert_eq!(project(&document, &["/a/c/1", "/empty"]), r#"{"a":{"c":[null,20]},"empty":{}}"#);
+    assert_eq!(project(&document, &["/*/h", "/*/*/*/d"]), r#"{"a":{"c":[null,null,{"d":2}]},"g":{"h":5}}"#);
+
+    // Pointers that lead nowhere keep nothing, not even the way there
+    assert_eq!(project(&document, &["/f/x", "/g/x", "/empty/x", "/missing", "/a/c/5", "/a/c/2/x"]), "{}");
+}
+
+
+#[test]
+fn keeps_array_positions()
+{
+    // The same value at a different index is a different projection
+    let first = project(&json!({ "a": ["x", "y", "z"] }), &["/a/2"]);
+    let second = project(&json!({ "a": ["z"] }), &["/a/0"]);
+
+    assert_eq!(first, r#"{"a":[null,null,"z"]}"#);
+    assert_eq!(second, r#"{"a":["z"]}"#);
+    assert_ne!(first, second);
+
+    // A selected null stays, while elements left out after the last kept one are not written
+    assert_eq!(project(&json!({ "a": [1, null, 3, 4] }), &["/a/1"]), r#"{"a":[null,null]}"#);
+    assert_eq!(project(&json!({ "a": [1, 2, 3] }), &["/a/0"]), r#"{"a":[1]}"#);
+}
+
+
+#[test]
+fn combines_with_other_rules()
+{
+    #[derive(Serialize)]
+    struct Statement
+    {
+        subject: Vec<&'static str>,
+        predicate: serde_json::Value,
+    }
+
+    let statement = Statement { subject: vec!["b", "a"], predicate: json!({ "materials": [{ "uri": "x", "note": "y" }], "other": 1 }) };
+
+    let formatter = CanonicalFormatter::new()
+        .with_exclusion("/predicate/materials/*/note").unwrap()
+        .with_array_sort("/subject", crate::ArraySort::CanonicalBytes { dedup: false }).unwrap();
+
+    let bytes = projection::to_vec(&statement, &["/subject", "/predicate/materials"], formatter).unwrap();
+
+    assert_eq!(bytes, br#"{"predicate":{"materials":[{"uri":"x"}]},"subject":["a","b"]}"#);
+}
+
+
+#[test]
+fn invalid_pointers()
+{
+    let document = json!({ "subject": ["a"] });
+
+    assert!(projection::to_vec(&document, &["subject"], CanonicalFormatter::new()).is_err());
+    assert!(projection::to_vec(&document, &["/subject", "/a~b"], CanonicalFormatter::new()).is_err());
+    assert!(CanonicalFormatter::new().with_array_sort("subject", crate::ArraySort::CanonicalBytes { dedup: false }).is_err());
+    assert!(CanonicalFormatter::new().with_exclusion("subject").is_err());
+}
+
+
+#[cfg(feature = "jws")]
+#[test]
+fn verify_signature()
+{
+    use ed25519_dalek::{Signer, SigningKey, Verifier};
+
+    let key = SigningKey::from_bytes(&[5; 32]);
+    let pointers = ["/subject", "/predicate/materials"];
+
+    let document = json!({ "subject": [{ "name": "app" }], "predicate": { "builder": "ci", "materials": ["src"] } });
+    let signature = key.sign(&projection::to_vec(&document, &pointers, CanonicalFormatter::new()).unwrap());
+
+    let verify = |document: &serde_json::Value| projection::verify(document, &pointers, CanonicalFormatter::new(), |bytes| key.verifying_key().verify(bytes, &signature).is_ok());
+
+    let mut unselected = document.clone();
+    unselected["predicate"]["builder"] = json!("laptop");
+
+    verify(&document).unwrap();
+    verify(&unselected).unwrap();
+
+    let mut selected = document.clone();
+    selected["predicate"]["materials"][0] = json!("tampered");
+
+    assert!(verify(&selected).is_err());
+}
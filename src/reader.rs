@@ -0,0 +1,149 @@
+//! # Canonicalizing reader
+//!
+//! An [io::Read] adapter whose reads yield the canonical form of the JSON text read from another
+//! reader, so it can be hashed and stored in one pass.
+//!
+//! ```rust
+//! use std::io::Read;
+//! use serde_canonical_json::{CanonicalFormatter, CanonicalizingReader};
+//!
+//! let input = r#"{ "b": [1, 2], "a": "x" }"#.as_bytes();
+//!
+//! let mut canonical = String::new();
+//! CanonicalizingReader::new(input, CanonicalFormatter::new()).read_to_string(&mut canonical).unwrap();
+//!
+//! assert_eq!(canonical, r#"{"a":"x","b":[1,2]}"#);
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use crate::CanonicalFormatter;
+use crate::text::Parser;
+
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+
+/// Reads JSON text from `R` in chunks, and yields it canonically.
+///
+/// Each object is held until it closes, to sort its members. Input whose keys are sorted already
+/// can be read with [with_sorted_keys](Self::with_sorted_keys) instead, which holds little more than
+/// a chunk and the token being read.
+pub struct CanonicalizingReader<R>
+{
+    inner: R,
+    formatter: CanonicalFormatter,
+    parser: Parser,
+    input: Box<[u8]>,
+    output: Vec<u8>,
+    position: usize,
+    finished: bool,
+    failed: bool,
+}
+
+
+impl<R> CanonicalizingReader<R>
+    where
+        R: io::Read,
+{
+    /// Creates a reader that canonicalizes what it reads from `inner` with the rules of `formatter`.
+    pub fn new(inner: R, formatter: CanonicalFormatter) -> Self
+    {
+        Self
+        {
+            inner,
+            formatter,
+            parser: Parser::new(),
+            input: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+            failed: false,
+        }
+    }
+
+
+    /// Creates a reader for input whose object keys are in the formatter's order already, which
+    /// writes members as they arrive instead of holding each object.
+    ///
+    /// A key that arrives out of order fails the read with [CanonicalError::UnsortedKey](crate::CanonicalError::UnsortedKey).
+    /// Formatters that drop or check members once their object is known, such as those with
+    /// selections, exclusions, elision or the JavaScript, DAG-JSON and SSB profiles, still hold objects.
+    pub fn with_sorted_keys(inner: R, formatter: CanonicalFormatter) -> Self
+    {
+        Self::new(inner, formatter.with_sorted_input())
+    }
+
+
+    pub fn get_ref(&self) -> &R
+    {
+        &self.inner
+    }
+
+
+    pub fn into_inner(self) -> R
+    {
+        self.inner
+    }
+
+
+    /// Reads and canonicalizes input until some output is ready or the input ends.
+    fn fill(&mut self) -> io::Result<()>
+    {
+        self.output.clear();
+        self.position = 0;
+
+        while self.output.is_empty() && !self.finished
+        {
+            let read = self.inner.read(&mut self.input)?;
+
+            let result = if read == 0
+            {
+                self.finished = true;
+                self.parser.finish(&mut self.formatter, &mut self.output)
+            }
+            else
+            {
+                self.parser.feed(&self.input[..read], &mut self.formatter, &mut self.output)
+            };
+
+            if let Err(error) = result
+            {
+                self.failed = true;
+                return Err(error)
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+impl<R> io::Read for CanonicalizingReader<R>
+    where
+        R: io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+    {
+        if self.failed
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "An earlier read failed."))
+        }
+
+        if self.position == self.output.len()
+        {
+            self.fill()?;
+        }
+
+        let available = &self.output[self.position..];
+        let length = available.len().min(buf.len());
+
+        buf[..length].copy_from_slice(&available[..length]);
+        self.position += length;
+
+        Ok(length)
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
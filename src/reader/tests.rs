@@ -0,0 +1,122 @@
+use std::io::Read;
+use crate::{CanonicalError, CanonicalFormatter, CanonicalizingReader, IJsonPolicy, Limits, canonicalize_str};
+
+
+/// Hands out its input a few bytes at a time, counting how much was taken.
+struct Trickle<'a>
+{
+    input: &'a [u8],
+    size: usize,
+    taken: usize,
+}
+
+
+impl Read for Trickle<'_>
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>
+    {
+        let length = self.size.min(buf.len()).min(self.input.len() - self.taken);
+
+        buf[..length].copy_from_slice(&self.input[self.taken..self.taken + length]);
+        self.taken += length;
+
+        Ok(length)
+    }
+}
+
+
+fn read_all(reader: &mut impl Read, size: usize) -> std::io::Result<Vec<u8>>
+{
+    let mut output = Vec::new();
+    let mut buf = vec![0; size];
+
+    loop
+    {
+        match reader.read(&mut buf)?
+        {
+            0 => return Ok(output),
+            read => output.extend_from_slice(&buf[..read]),
+        }
+    }
+}
+
+
+#[test]
+fn any_chunk_size()
+{
+    let json = r#" { "c" : [ 1, -12, true, null, [ ] ], "b" : { "z" : "😀", "y" : { } }, "a" : "x\ty\/z\u0001\"\\" } "#;
+    let expected = canonicalize_str(json, CanonicalFormatter::new()).unwrap();
+
+    for size in 1..=json.len()
+    {
+        let mut reader = CanonicalizingReader::new(Trickle { input: json.as_bytes(), size, taken: 0 }, CanonicalFormatter::new());
+
+        assert_eq!(read_all(&mut reader, size).unwrap(), expected, "{}", size);
+    }
+}
+
+
+#[test]
+fn sorted_keys()
+{
+    let json = r#"{ "a" : { "\"" : [ { "x" : 1 }, { } ], "\\" : null }, "aa" : "é", "b" : [ ] }"#;
+    let expected = canonicalize_str(json, CanonicalFormatter::new()).unwrap();
+
+    for size in [1, 2, 7, json.len()]
+    {
+        let mut reader = CanonicalizingReader::with_sorted_keys(Trickle { input: json.as_bytes(), size, taken: 0 }, CanonicalFormatter::new());
+
+        assert_eq!(read_all(&mut reader, size).unwrap(), expected, "{}", size);
+    }
+}
+
+
+#[test]
+fn sorted_keys_stream()
+{
+    let members: Vec<_> = (0..1000).map(|index| format!(r#""{:04}": [{{ "id": {} }}]"#, index, index)).collect();
+    let json = format!("{{ {} }}", members.join(", "));
+
+    let mut reader = CanonicalizingReader::with_sorted_keys(Trickle { input: json.as_bytes(), size: 64, taken: 0 }, CanonicalFormatter::new());
+    let mut start = [0; 16];
+    reader.read_exact(&mut start).unwrap();
+
+    assert_eq!(&start, br#"{"0000":[{"id":0"#);
+    assert_eq!(reader.get_ref().taken, 64);
+
+    // Without the promise, the object is held until it closes
+    let mut reader = CanonicalizingReader::new(Trickle { input: json.as_bytes(), size: 64, taken: 0 }, CanonicalFormatter::new().with_limits(Limits { buffered_bytes: Some(1024), ..Limits::default() }));
+    let error = reader.read_exact(&mut start).unwrap_err();
+
+    assert_eq!(CanonicalError::from_io(&error), Some(&CanonicalError::TooLarge(1024)));
+}
+
+
+#[test]
+fn unsorted_keys()
+{
+    for (json, formatter, error) in
+    [
+        (r#"{"a":{"c":1,"b":2}}"#, CanonicalFormatter::new(), CanonicalError::UnsortedKey("b".to_owned())),
+        (r#"{"a":1,"a":2}"#, CanonicalFormatter::new().with_ijson(IJsonPolicy::Strict), CanonicalError::DuplicateKey("a".to_owned())),
+    ]
+    {
+        let mut reader = CanonicalizingReader::with_sorted_keys(json.as_bytes(), formatter);
+
+        assert_eq!(CanonicalError::from_io(&read_all(&mut reader, 64).unwrap_err()), Some(&error), "{}", json);
+        assert!(reader.read(&mut [0; 8]).is_err());
+    }
+}
+
+
+#[test]
+fn invalid_json()
+{
+    let mut reader = CanonicalizingReader::new(Trickle { input: b"[1, 2 3]", size: 2, taken: 0 }, CanonicalFormatter::new());
+    let error = read_all(&mut reader, 4).unwrap_err();
+
+    assert_eq!(CanonicalError::from_io(&error), Some(&CanonicalError::InvalidJson { offset: 6, reason: "Expected ',' or ']'" }));
+
+    let mut reader = CanonicalizingReader::new(&b"{\"a\":"[..], CanonicalFormatter::new());
+    assert!(read_all(&mut reader, 4).is_err());
+}
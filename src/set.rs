@@ -0,0 +1,96 @@
+//! # Sets
+//!
+//! A serde `with` adapter that writes a collection standing for a set, such as a [HashSet](std::collections::HashSet),
+//! with its elements in a single order, whatever order it iterates in.
+//!
+//! The [CanonicalFormatter](crate::CanonicalFormatter) writing the collection orders the elements by the bytes it writes for them,
+//! as [ArraySort::CanonicalBytes](crate::ArraySort::CanonicalBytes) does, so the order follows its
+//! profile. Any other serializer gets them in iteration order.
+//!
+//! ```rust
+//! use std::collections::HashSet;
+//! use serde::Serialize;
+//! use serde_json::Serializer;
+//! use serde_canonical_json::CanonicalFormatter;
+//!
+//! #[derive(Serialize)]
+//! struct Role
+//! {
+//!     #[serde(with = "serde_canonical_json::set")]
+//!     permissions: HashSet<&'static str>,
+//!     #[serde(with = "serde_canonical_json::set::dedup")]
+//!     groups: Vec<&'static str>,
+//! }
+//!
+//! let role = Role { permissions: HashSet::from(["write", "read", "delete"]), groups: vec!["staff", "admin", "staff"] };
+//!
+//! let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::new());
+//! role.serialize(&mut ser).unwrap();
+//!
+//! assert_eq!(ser.into_inner(), br#"{"groups":["admin","staff"],"permissions":["delete","read","write"]}"#);
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+
+/// Writes the elements of `value` in the order of their canonical bytes.
+pub fn serialize<'a, T, S>(value: &'a T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        &'a T: IntoIterator,
+        <&'a T as IntoIterator>::Item: Serialize,
+        S: Serializer,
+{
+    serialize_sorted(value, serializer, false)
+}
+
+
+/// Reads the collection as it would be read without the adapter.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+{
+    T::deserialize(deserializer)
+}
+
+
+/// The same adapter, keeping only the first of several identical elements.
+pub mod dedup
+{
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+
+    /// Writes the distinct elements of `value` in the order of their canonical bytes.
+    pub fn serialize<'a, T, S>(value: &'a T, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            &'a T: IntoIterator,
+            <&'a T as IntoIterator>::Item: Serialize,
+            S: Serializer,
+    {
+        super::serialize_sorted(value, serializer, true)
+    }
+
+
+    /// Reads the collection as it would be read without the adapter.
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where
+            T: Deserialize<'de>,
+            D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer)
+    }
+}
+
+
+fn serialize_sorted<'a, T, S>(value: &'a T, serializer: S, dedup: bool) -> Result<S::Ok, S::Error>
+    where
+        &'a T: IntoIterator,
+        <&'a T as IntoIterator>::Item: Serialize,
+        S: Serializer,
+{
+    crate::hint::set(dedup, || serializer.collect_seq(value))
+}
+
+
+#[cfg(test)]
+mod tests;
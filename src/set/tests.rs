@@ -0,0 +1,142 @@
+use std::collections::{BTreeSet, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::Serializer;
+use crate::{CanonicalFormatter, Profile};
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Document
+{
+    #[serde(with = "crate::set")]
+    tags: HashSet<String>,
+    #[serde(with = "crate::set")]
+    scores: Vec<f64>,
+    #[serde(with = "crate::set::dedup")]
+    owners: Vec<Owner>,
+}
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Owner
+{
+    name: String,
+    id: u32,
+}
+
+
+fn serialize<T: Serialize>(value: &T, formatter: CanonicalFormatter) -> String
+{
+    let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser).unwrap();
+
+    String::from_utf8(ser.into_inner()).unwrap()
+}
+
+
+#[test]
+fn sets_have_one_order()
+{
+    let owners = vec![Owner { name: "b".to_owned(), id: 2 }, Owner { name: "a".to_owned(), id: 10 }, Owner { name: "b".to_owned(), id: 2 }];
+    let expected = r#"{"owners":[{"id":10,"name":"a"},{"id":2,"name":"b"}],"scores":[-1.5,0.25,10],"tags":["\n","a","b","c"]}"#;
+
+    // Each HashSet has its own random iteration order
+    for _ in 0..8
+    {
+        let document = Document
+        {
+            tags: ["c", "a", "\n", "b"].into_iter().map(str::to_owned).collect(),
+            scores: vec![10.0, -1.5, 0.25],
+            owners: owners.clone(),
+        };
+
+        assert_eq!(serialize(&document, CanonicalFormatter::with_profile(Profile::JavaScript)), expected);
+    }
+
+    let document: Document = serde_json::from_str(expected).unwrap();
+    assert_eq!(document.owners.len(), 2);
+}
+
+
+#[test]
+fn any_collection()
+{
+    #[derive(Serialize)]
+    struct Collections
+    {
+        #[serde(with = "crate::set")]
+        ordered: BTreeSet<i64>,
+        #[serde(with = "crate::set::dedup")]
+        list: Vec<&'static str>,
+    }
+
+    let collections = Collections { ordered: BTreeSet::from([3, -20, 100]), list: vec!["z", "y", "z"] };
+
+    // Orders by bytes, not by value
+    assert_eq!(serialize(&collections, CanonicalFormatter::new()), r#"{"list":["y","z"],"ordered":[-20,100,3]}"#);
+}
+
+
+#[test]
+fn ordered_by_the_active_profile()
+{
+    #[derive(Serialize)]
+    struct Letters
+    {
+        #[serde(with = "crate::set::dedup")]
+        letters: Vec<&'static str>,
+        nested: Vec<Nested>,
+    }
+
+    #[derive(Serialize)]
+    struct Nested
+    {
+        #[serde(with = "crate::set")]
+        letters: HashSet<&'static str>,
+        plain: Vec<&'static str>,
+    }
+
+    let letters = Letters { letters: vec!["z", "\u{e9}", "z"], nested: vec![Nested { letters: HashSet::from(["z", "\u{e9}"]), plain: vec!["z", "\u{e9}"] }] };
+
+    // Escaped, "\u00e9" sorts before "z", but its UTF-8 bytes sort after
+    assert_eq!(
+        serialize(&letters, CanonicalFormatter::with_profile(Profile::Python { ensure_ascii: true })),
+        r#"{"letters":["\u00e9","z"],"nested":[{"letters":["\u00e9","z"],"plain":["z","\u00e9"]}]}"#,
+    );
+    assert_eq!(
+        serialize(&letters, CanonicalFormatter::with_profile(Profile::Python { ensure_ascii: false })),
+        "{\"letters\":[\"z\",\"\u{e9}\"],\"nested\":[{\"letters\":[\"z\",\"\u{e9}\"],\"plain\":[\"z\",\"\u{e9}\"]}]}",
+    );
+
+    // Other serializers get the elements as they are
+    let letters = Letters { letters: vec!["z", "\u{e9}", "z"], nested: Vec::new() };
+    assert_eq!(serde_json::to_string(&letters).unwrap(), "{\"letters\":[\"z\",\"\u{e9}\",\"z\"],\"nested\":[]}");
+}
+
+
+#[test]
+fn panics_do_not_leave_a_set_behind()
+{
+    struct Panics;
+
+    impl Serialize for Panics
+    {
+        fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+        {
+            panic!("element failed")
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Failing
+    {
+        #[serde(with = "crate::set")]
+        elements: Vec<Panics>,
+    }
+
+    // A plain serializer never takes the mark, so only resetting it on unwind clears it
+    let result = std::panic::catch_unwind(|| serde_json::to_string(&Failing { elements: vec![Panics] }));
+    assert!(result.is_err());
+
+    // The next array on this thread is not sorted as a set
+    assert_eq!(serialize(&["b", "a"], CanonicalFormatter::new()), r#"["b","a"]"#);
+}
@@ -0,0 +1,201 @@
+//! # Secure Scuttlebutt messages
+//!
+//! Signs and verifies [Secure Scuttlebutt](https://ssbc.github.io/scuttlebutt-protocol-guide/) feed
+//! messages and computes their ids.
+//!
+//! SSB signs `JSON.stringify(message, null, 2)` without the `signature` member, so the bytes depend on
+//! the order of the members as well as their values. Messages are serialized with [Profile::Ssb], and
+//! held in an [OrderedValue], which keeps the order they were parsed in whatever serde_json features
+//! are enabled.
+//!
+//! ```rust
+//! use ed25519_dalek::SigningKey;
+//! use serde_canonical_json::ssb::{self, OrderedValue};
+//!
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//!
+//! let mut message: OrderedValue = serde_json::from_str(&format!(r#"{{
+//!     "previous": null,
+//!     "author": "{}",
+//!     "sequence": 1,
+//!     "timestamp": 1700000000000,
+//!     "hash": "sha256",
+//!     "content": {{ "type": "post", "text": "Hello" }}
+//! }}"#, ssb::feed_id(&key.verifying_key()))).unwrap();
+//!
+//! ssb::sign_message(&mut message, &key).unwrap();
+//! ssb::verify_message(&message).unwrap();
+//!
+//! assert!(ssb::message_id(&message).unwrap().ends_with(".sha256"));
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use indexmap::IndexMap;
+use serde::{Serialize, Deserialize};
+use serde_json::Number;
+use sha2::{Digest, Sha256};
+use crate::{CanonicalFormatter, Profile, to_vec_with_formatter};
+
+
+/// A JSON value whose objects keep their members in the order they were parsed or inserted in.
+///
+/// A member that appears twice keeps the position of the first and the value of the last, as with
+/// `JSON.parse`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum OrderedValue
+{
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<OrderedValue>),
+    Object(IndexMap<String, OrderedValue>),
+}
+
+
+impl OrderedValue
+{
+    /// The value of the member `key`, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&OrderedValue>
+    {
+        self.as_object().and_then(|members| members.get(key))
+    }
+
+
+    pub fn as_str(&self) -> Option<&str>
+    {
+        match self
+        {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+
+    pub fn as_object(&self) -> Option<&IndexMap<String, OrderedValue>>
+    {
+        match self
+        {
+            Self::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+
+    pub fn as_object_mut(&mut self) -> Option<&mut IndexMap<String, OrderedValue>>
+    {
+        match self
+        {
+            Self::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+
+/// Serializes `message` the way `JSON.stringify(message, null, 2)` does.
+pub fn serialize_message<T>(message: &T) -> io::Result<String>
+    where
+        T: ?Sized + Serialize,
+{
+    let bytes = to_vec_with_formatter(message, CanonicalFormatter::with_profile(Profile::Ssb))?;
+
+    String::from_utf8(bytes).map_err(|_| Error::new(ErrorKind::InvalidData, "Message is not valid UTF-8."))
+}
+
+
+/// The id of the feed signed by `key`, such as `@...=.ed25519`.
+pub fn feed_id(key: &VerifyingKey) -> String
+{
+    format!("@{}.ed25519", STANDARD.encode(key.as_bytes()))
+}
+
+
+/// Signs `message` and appends the `signature` member, replacing any existing signature.
+pub fn sign_message(message: &mut OrderedValue, key: &SigningKey) -> io::Result<()>
+{
+    let mut unsigned = unsigned_members(message)?;
+
+    let signature = key.sign(serialize_message(&unsigned)?.as_bytes());
+    unsigned.insert("signature".to_owned(), OrderedValue::String(format!("{}.sig.ed25519", STANDARD.encode(signature.to_bytes()))));
+
+    *message = OrderedValue::Object(unsigned);
+
+    Ok(())
+}
+
+
+/// Checks that the `signature` of `message` is valid for its `author`.
+pub fn verify_message(message: &OrderedValue) -> io::Result<()>
+{
+    let Some(author) = message.get("author").and_then(OrderedValue::as_str) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Message author is missing."))
+    };
+
+    let key = author.strip_prefix('@')
+        .and_then(|author| author.strip_suffix(".ed25519"))
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+
+    let Some(key) = key else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Message author is not an ed25519 feed id."))
+    };
+
+    let signature = message.get("signature")
+        .and_then(OrderedValue::as_str)
+        .and_then(|signature| signature.strip_suffix(".sig.ed25519"))
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok());
+
+    let Some(signature) = signature else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Message signature is missing or not an ed25519 signature."))
+    };
+
+    let unsigned = serialize_message(&unsigned_members(message)?)?;
+
+    if key.verify_strict(unsigned.as_bytes(), &signature).is_err()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Message signature is invalid."))
+    }
+
+    Ok(())
+}
+
+
+/// The id of a signed message, such as `%...=.sha256`.
+///
+/// The legacy format hashes the low byte of every UTF-16 code unit of the serialized message, as
+/// Node.js does for the `binary` encoding, rather than its UTF-8 bytes.
+pub fn message_id(message: &OrderedValue) -> io::Result<String>
+{
+    let serialized = serialize_message(message)?;
+    let bytes: Vec<u8> = serialized.encode_utf16().map(|unit| unit as u8).collect();
+
+    Ok(format!("%{}.sha256", STANDARD.encode(Sha256::digest(bytes))))
+}
+
+
+/// The members of `message` other than `signature`, in their original order.
+fn unsigned_members(message: &OrderedValue) -> io::Result<IndexMap<String, OrderedValue>>
+{
+    let Some(message) = message.as_object() else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Message must be an object."))
+    };
+
+    Ok(message.iter()
+        .filter(|(key, _)| *key != "signature")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect())
+}
+
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,97 @@
+use ed25519_dalek::SigningKey;
+use crate::ssb::{self, OrderedValue};
+
+
+// The first message is the example from the Scuttlebutt protocol guide. The second was signed and
+// hashed with Node.js 20, using JSON.stringify(message, null, 2) and the ed25519 key with seed 9
+
+const GUIDE_MESSAGE: &str = r#"{
+  "previous": "%XphMUkWQtomKjXQvFGfsGYpt69sgEY7Y4Vou9cEuJho=.sha256",
+  "author": "@FCX/tsDLpubCPKKfIrw4gc+SQkHcaD17s7GI6i/ziWY=.ed25519",
+  "sequence": 2,
+  "timestamp": 1514517078157,
+  "hash": "sha256",
+  "content": {
+    "type": "post",
+    "text": "Second post!"
+  },
+  "signature": "z7W1ERg9UYZjNfE72ZwEuJF79khG+eOHWFp6iF+KLuSrw8Lqa6IousK4cCn9T5qFa8E14GVek4cAMmMbjqDnAg==.sig.ed25519"
+}"#;
+const GUIDE_ID: &str = "%R7lJEkz27lNijPhYNDzYoPjM0Fp+bFWzwX0SmNJB/ZE=.sha256";
+
+const UNSIGNED: &str = r#"{
+  "previous": "%R7lJEkz27lNijPhYNDzYoPjM0Fp+bFWzwX0SmNJB/ZE=.sha256",
+  "sequence": 3,
+  "author": "@/RckOFqgx1tk+3jNYC+h2ZH96/drE8WO1wLqyDXp9hg=.ed25519",
+  "timestamp": 1514517078157.5,
+  "hash": "sha256",
+  "content": {
+    "type": "post",
+    "text": "héllo 🌍\n\"quoted\" ",
+    "mentions": [],
+    "root": null,
+    "nested": [
+      [
+        1,
+        2.5e-7
+      ],
+      {},
+      {
+        "z": 1e+21,
+        "a": true
+      }
+    ]
+  }
+}"#;
+const SIGNATURE: &str = "SDHsrSv12FOgHXysGvjhUBXtEZ89+oJIUq7r5u5iXsHfs8x1Xro0Zs1hZrx0eohyMe2pQ/TcG1EPNoGetm1mDg==.sig.ed25519";
+const ID: &str = "%vMMZFJO8Rxy9UjH+0Dj0AqErROmrXzdImpAFTYjBbrA=.sha256";
+
+
+#[test]
+fn guide_message()
+{
+    let message: OrderedValue = serde_json::from_str(GUIDE_MESSAGE).unwrap();
+
+    assert_eq!(ssb::serialize_message(&message).unwrap(), GUIDE_MESSAGE);
+    assert_eq!(ssb::message_id(&message).unwrap(), GUIDE_ID);
+
+    ssb::verify_message(&message).unwrap();
+}
+
+
+#[test]
+fn sign_message()
+{
+    let mut message: OrderedValue = serde_json::from_str(UNSIGNED).unwrap();
+
+    // Member order is kept, including "sequence" before "author"
+    assert_eq!(ssb::serialize_message(&message).unwrap(), UNSIGNED);
+
+    let key = SigningKey::from_bytes(&[9; 32]);
+    ssb::sign_message(&mut message, &key).unwrap();
+
+    assert_eq!(message.get("signature").and_then(OrderedValue::as_str), Some(SIGNATURE));
+    assert_eq!(message.as_object().unwrap().keys().next_back().unwrap(), "signature");
+
+    // Non-ASCII content is hashed by the low byte of each UTF-16 unit
+    assert_eq!(ssb::message_id(&message).unwrap(), ID);
+
+    ssb::verify_message(&message).unwrap();
+}
+
+
+#[test]
+fn verify_rejects_changes()
+{
+    let message: OrderedValue = serde_json::from_str(&GUIDE_MESSAGE.replace("Second post!", "Third post!")).unwrap();
+
+    assert!(ssb::verify_message(&message).is_err());
+
+    // Reordering members changes the signed bytes
+    let message = GUIDE_MESSAGE.replace(r#""sequence": 2,
+  "timestamp": 1514517078157,"#, r#""timestamp": 1514517078157,
+  "sequence": 2,"#);
+    let message: OrderedValue = serde_json::from_str(&message).unwrap();
+
+    assert!(ssb::verify_message(&message).is_err());
+}
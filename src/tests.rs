@@ -1,7 +1,7 @@
 use serde::{Serialize, Deserialize};
 use serde_json::{self, Serializer};
 use std::collections::HashMap;
-use crate::CanonicalFormatter;
+use crate::{ArraySort, CanonicalError, CanonicalFormatter, ElisionPolicy, EscapePolicy, IJsonPolicy, Limits, Profile, canonicalize_str};
 
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -56,4 +56,477 @@ fn canonical()
     let deserialized: TestStruct1 = serde_json::from_str(&string).unwrap();
     
     assert_eq!(dut, deserialized);
-}
\ No newline at end of file
+}
+
+
+#[test]
+fn nostr_profile()
+{
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Nostr));
+    (0, "line\nbreak \"quoted\"\t\u{1}").serialize(&mut ser).unwrap();
+    let string = String::from_utf8(ser.into_inner()).unwrap();
+
+    assert_eq!(string, "[0,\"line\\nbreak \\\"quoted\\\"\\t\u{1}\"]");
+}
+
+
+#[test]
+fn dag_json_profile()
+{
+    let mut map = HashMap::new();
+    map.insert("bb".to_owned(), 1.0);
+    map.insert("a\u{e9}".to_owned(), 0.5);
+    map.insert("c".to_owned(), -2.5e-7);
+
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::DagJson));
+    map.serialize(&mut ser).unwrap();
+    let string = String::from_utf8(ser.into_inner()).unwrap();
+
+    // Keys sort by their byte length first, so the two byte "bb" precedes the three byte "aé"
+    assert_eq!(string, "{\"c\":-2.5e-7,\"bb\":1.0,\"a\u{e9}\":0.5}");
+}
+
+
+#[test]
+fn python_profile()
+{
+    // Recorded from Python 3.11's json.dumps(input, sort_keys=True, separators=(",", ":"), ensure_ascii=...)
+    let fixtures: Vec<serde_json::Value> = serde_json::from_str(include_str!("../tests/fixtures/python.json")).unwrap();
+
+    for fixture in fixtures
+    {
+        for (ensure_ascii, expected) in [(true, &fixture["ensure_ascii"]), (false, &fixture["unicode"])]
+        {
+            let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Python { ensure_ascii }));
+            fixture["input"].serialize(&mut ser).unwrap();
+            let string = String::from_utf8(ser.into_inner()).unwrap();
+
+            assert_eq!(&string, expected.as_str().unwrap());
+        }
+    }
+}
+
+
+#[test]
+fn javascript_profile()
+{
+    // Recorded from Node.js 20 with fast-json-stable-stringify's algorithm, with null members made undefined
+    let fixtures: Vec<serde_json::Value> = serde_json::from_str(include_str!("../tests/fixtures/javascript.json")).unwrap();
+
+    for fixture in fixtures
+    {
+        let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::JavaScript));
+        fixture["input"].serialize(&mut ser).unwrap();
+        let string = String::from_utf8(ser.into_inner()).unwrap();
+
+        assert_eq!(&string, fixture["output"].as_str().unwrap());
+    }
+}
+
+
+#[test]
+fn jcs_profile()
+{
+    // tests/fixtures/jcs.js regenerates the expected output with the RFC 8785 reference implementation
+    let fixtures: Vec<serde_json::Value> = serde_json::from_str(include_str!("../tests/fixtures/jcs.json")).unwrap();
+
+    for fixture in fixtures
+    {
+        let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Jcs));
+        fixture["input"].serialize(&mut ser).unwrap();
+        let string = String::from_utf8(ser.into_inner()).unwrap();
+
+        assert_eq!(&string, fixture["output"].as_str().unwrap());
+    }
+}
+
+
+#[test]
+fn go_profile()
+{
+    // Follows Go 1.22's encoding/json; tests/fixtures/go.go regenerates the expected output
+    let fixtures: Vec<serde_json::Value> = serde_json::from_str(include_str!("../tests/fixtures/go.json")).unwrap();
+
+    for fixture in fixtures
+    {
+        for (escape_html, expected) in [(true, &fixture["escape_html"]), (false, &fixture["no_escape_html"])]
+        {
+            let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Go { escape_html }));
+            fixture["input"].serialize(&mut ser).unwrap();
+            let string = String::from_utf8(ser.into_inner()).unwrap();
+
+            assert_eq!(&string, expected.as_str().unwrap());
+        }
+    }
+
+    // float32 keeps its own shortest digits, and []byte is a padded base64 string
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Go { escape_html: true }));
+    (0.1f32, 1e-7f32, 3e38f32).serialize(&mut ser).unwrap();
+    serde::Serializer::serialize_bytes(&mut ser, b"hi").unwrap();
+    let string = String::from_utf8(ser.into_inner()).unwrap();
+
+    assert_eq!(string, "[0.1,1e-7,3e+38]\"aGk=\"");
+}
+
+
+#[test]
+fn amino_profile()
+{
+    let mut map = HashMap::new();
+    map.insert(10u64, (1u32, 2u64, -3i64, "<&>"));
+
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Amino));
+    map.serialize(&mut ser).unwrap();
+    let string = String::from_utf8(ser.into_inner()).unwrap();
+
+    // Integer map keys are already strings, and only 64-bit values are quoted
+    assert_eq!(string, r#"{"10":[1,"2","-3","\u003c\u0026\u003e"]}"#);
+
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Amino));
+    assert!(1.5.serialize(&mut ser).is_err());
+}
+
+
+#[test]
+fn ssb_profile()
+{
+    #[derive(Serialize)]
+    struct Message
+    {
+        z: Option<u8>,
+        a: Vec<Vec<f64>>,
+        m: HashMap<String, bool>,
+    }
+
+    let message = Message { z: None, a: vec![vec![], vec![1.0, 0.5]], m: HashMap::new() };
+
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Ssb));
+    message.serialize(&mut ser).unwrap();
+    let string = String::from_utf8(ser.into_inner()).unwrap();
+
+    // Fields keep their declaration order, and empty containers stay on one line
+    assert_eq!(string, "{\n  \"z\": null,\n  \"a\": [\n    [],\n    [\n      1,\n      0.5\n    ]\n  ],\n  \"m\": {}\n}");
+}
+
+
+#[test]
+fn ijson_policy()
+{
+    fn check<T: Serialize>(value: &T, policy: IJsonPolicy) -> Result<String, CanonicalError>
+    {
+        let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::new().with_ijson(policy));
+
+        match value.serialize(&mut ser)
+        {
+            Ok(()) => Ok(String::from_utf8(ser.into_inner()).unwrap()),
+            Err(error) => Err(std::io::Error::from(error).get_ref().unwrap().downcast_ref::<CanonicalError>().unwrap().clone()),
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Flattened
+    {
+        a: u8,
+        #[serde(flatten)]
+        extra: HashMap<String, u8>,
+    }
+
+    let duplicate = Flattened { a: 1, extra: HashMap::from([("a".to_owned(), 2)]) };
+    assert_eq!(check(&duplicate, IJsonPolicy::Strict), Err(CanonicalError::DuplicateKey("a".to_owned())));
+
+    assert_eq!(check(&(9007199254740991u64, -9007199254740991i64), IJsonPolicy::Strict).unwrap(), "[9007199254740991,-9007199254740991]");
+    assert_eq!(check(&-9007199254740992i128, IJsonPolicy::Strict), Err(CanonicalError::UnsafeInteger("-9007199254740992".to_owned())));
+    assert_eq!(check(&[u64::MAX], IJsonPolicy::StringifyLargeIntegers).unwrap(), "[\"18446744073709551615\"]");
+
+    // Integer map keys are strings already
+    assert_eq!(check(&HashMap::from([(u64::MAX, 1)]), IJsonPolicy::StringifyLargeIntegers).unwrap(), "{\"18446744073709551615\":1}");
+
+    assert_eq!(check(&"a\u{FDD0}", IJsonPolicy::Strict), Err(CanonicalError::Noncharacter('\u{FDD0}')));
+    assert_eq!(check(&HashMap::from([("\u{10FFFF}", 1)]), IJsonPolicy::Strict), Err(CanonicalError::Noncharacter('\u{10FFFF}')));
+
+    let raw = |json: &str| serde_json::value::RawValue::from_string(json.to_owned()).unwrap();
+
+    assert_eq!(check(&raw(r#"["\\ud800", "😀"]"#), IJsonPolicy::Strict).unwrap(), r#"["\\ud800", "😀"]"#);
+    assert_eq!(check(&raw(r#"["😀", "\udc00"]"#), IJsonPolicy::Strict), Err(CanonicalError::LoneSurrogate(0xDC00)));
+    assert_eq!(check(&raw(r#"["\ud800x"]"#), IJsonPolicy::Strict), Err(CanonicalError::LoneSurrogate(0xD800)));
+    assert_eq!(check(&raw(r#"{"a":[1,{"b":2,"b":3}]}"#), IJsonPolicy::Strict), Err(CanonicalError::DuplicateKey("b".to_owned())));
+    assert_eq!(check(&raw(r#"[9007199254740993]"#), IJsonPolicy::StringifyLargeIntegers), Err(CanonicalError::UnsafeInteger("9007199254740993".to_owned())));
+
+    // Integers beyond 64 bits are checked by their digits, not read as floats
+    assert_eq!(check(&raw(r#"{"\"1e999": [18446744073709551616]}"#), IJsonPolicy::StringifyLargeIntegers), Err(CanonicalError::UnsafeInteger("18446744073709551616".to_owned())));
+    assert_eq!(check(&raw(r#"[-9223372036854775809, 1e300]"#), IJsonPolicy::Strict), Err(CanonicalError::UnsafeInteger("-9223372036854775809".to_owned())));
+    assert_eq!(check(&raw(r#"[1.8446744073709551616e19, "18446744073709551616"]"#), IJsonPolicy::Strict).unwrap(), r#"[1.8446744073709551616e19, "18446744073709551616"]"#);
+    assert_eq!(canonicalize_str("[18446744073709551616]", CanonicalFormatter::new().with_ijson(IJsonPolicy::StringifyLargeIntegers)).unwrap(), br#"["18446744073709551616"]"#);
+}
+
+
+#[test]
+fn escape_policy()
+{
+    let mut map = HashMap::new();
+    map.insert("b<\n", "\u{e9}\u{1}\u{1F600}&");
+    map.insert("a\"", "\u{2028}\t");
+    map.insert("a", "x\u{7f}");
+
+    let expected = [
+        (EscapePolicy::Olpc, "{\"a\":\"x\u{7f}\",\"a\\\"\":\"\u{2028}\t\",\"b<\n\":\"\u{e9}\u{1}\u{1F600}&\"}"),
+        (EscapePolicy::Jcs, "{\"a\":\"x\u{7f}\",\"a\\\"\":\"\u{2028}\\t\",\"b<\\n\":\"\u{e9}\\u0001\u{1F600}&\"}"),
+        (EscapePolicy::AsciiOnly, "{\"a\":\"x\u{7f}\",\"a\\\"\":\"\\u2028\\t\",\"b<\\n\":\"\\u00e9\\u0001\\ud83d\\ude00&\"}"),
+        (EscapePolicy::HtmlSafe, "{\"a\":\"x\u{7f}\",\"a\\\"\":\"\\u2028\\t\",\"b\\u003c\\n\":\"\u{e9}\\u0001\u{1F600}\\u0026\"}"),
+    ];
+
+    // Keys and values are escaped alike, and the member order does not depend on the policy
+    for (policy, expected) in expected
+    {
+        let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::new().with_escape_policy(policy));
+        map.serialize(&mut ser).unwrap();
+        let string = String::from_utf8(ser.into_inner()).unwrap();
+
+        assert_eq!(string, expected, "{:?}", policy);
+    }
+
+    // The policy overrides the profile's own escaping
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::with_profile(Profile::Nostr).with_escape_policy(EscapePolicy::Olpc));
+    "line\nbreak".serialize(&mut ser).unwrap();
+
+    assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), "\"line\nbreak\"");
+}
+
+
+#[cfg(feature = "nfc")]
+#[test]
+fn nfc_normalization()
+{
+    use crate::NormalizationPolicy;
+
+    let mut map = HashMap::new();
+    map.insert("e\u{301}", "A\u{30A}");
+    map.insert("z", "\u{212B}");
+
+    // The decomposed key sorts after "z" once it is composed
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::new().with_normalization(NormalizationPolicy::Normalize));
+    map.serialize(&mut ser).unwrap();
+
+    assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), "{\"z\":\"\u{C5}\",\"\u{E9}\":\"\u{C5}\"}");
+
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::new().with_normalization(NormalizationPolicy::Reject));
+    let error = map.serialize(&mut ser).unwrap_err();
+
+    assert_eq!(std::io::Error::from(error).get_ref().unwrap().downcast_ref::<CanonicalError>(), Some(&CanonicalError::NotNormalized));
+
+    // Raw fragments are written again, so escaped decompositions are covered too
+    let raw = serde_json::value::RawValue::from_string(r#"[{"z":1, "e\u0301":"A\u030a"}]"#.to_owned()).unwrap();
+
+    let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::new().with_normalization(NormalizationPolicy::Normalize));
+    raw.serialize(&mut ser).unwrap();
+
+    assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), "[{\"z\":1,\"\u{E9}\":\"\u{C5}\"}]");
+}
+
+
+#[test]
+fn array_sort()
+{
+    // In alphabetical order, so SSB writes the same with and without preserve_order
+    let value = serde_json::json!({
+        "groups": [
+            { "members": [3, 1, 2, [9, 8]], "name": "x" },
+            { "members": [], "name": "w" },
+        ],
+        "log": ["z", "y"],
+        "tags": ["b", "a", "c", "a"],
+    });
+
+    let serialize = |formatter: CanonicalFormatter|
+    {
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        value.serialize(&mut ser).unwrap();
+
+        String::from_utf8(ser.into_inner()).unwrap()
+    };
+
+    let formatter = CanonicalFormatter::new()
+        .with_array_sort("/tags", ArraySort::CanonicalBytes { dedup: true }).unwrap()
+        .with_array_sort("/groups", ArraySort::CanonicalBytes { dedup: false }).unwrap()
+        .with_array_sort("/groups/*/members", ArraySort::CanonicalBytes { dedup: false }).unwrap();
+
+    // Elements are compared once their own objects are sorted, and arrays the rules miss keep their order
+    assert_eq!(serialize(formatter), r#"{"groups":[{"members":[1,2,3,[9,8]],"name":"x"},{"members":[],"name":"w"}],"log":["z","y"],"tags":["a","b","c"]}"#);
+
+    let formatter = CanonicalFormatter::with_profile(Profile::Ssb).with_array_sort("/log", ArraySort::CanonicalBytes { dedup: false }).unwrap();
+    assert_eq!(serialize(formatter), "{\n  \"groups\": [\n    {\n      \"members\": [\n        3,\n        1,\n        2,\n        [\n          9,\n          8\n        ]\n      ],\n      \"name\": \"x\"\n    },\n    {\n      \"members\": [],\n      \"name\": \"w\"\n    }\n  ],\n  \"log\": [\n    \"y\",\n    \"z\"\n  ],\n  \"tags\": [\n    \"b\",\n    \"a\",\n    \"c\",\n    \"a\"\n  ]\n}");
+}
+
+
+#[test]
+fn array_sort_by_member()
+{
+    let value = serde_json::json!({
+        "components": [
+            { "purl": "pkg:cargo/serde@1.0.0", "name": "serde" },
+            { "name": "unknown" },
+            { "purl": "pkg:cargo/regex@1.7.0", "name": "regex" },
+            "not an object",
+            { "purl": "pkg:cargo/libc@0.2.0", "name": "libc" },
+        ],
+        "rules": [
+            { "priority": 10, "allow": ["b", "a"] },
+            { "priority": 9, "allow": [] },
+            { "priority": -1, "allow": ["c"] },
+            { "priority": 10, "allow": ["a"] },
+        ],
+    });
+
+    let formatter = CanonicalFormatter::new()
+        .with_array_sort("/components", ArraySort::ByMember("purl".to_owned())).unwrap()
+        .with_array_sort("/rules", ArraySort::ByMember("priority".to_owned())).unwrap()
+        .with_array_sort("/rules/*/allow", ArraySort::CanonicalBytes { dedup: false }).unwrap();
+
+    let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser).unwrap();
+
+    // Numbers compare by value, and elements with equal members by their bytes
+    let expected = concat!(
+        r#"{"components":[{"name":"libc","purl":"pkg:cargo/libc@0.2.0"},{"name":"regex","purl":"pkg:cargo/regex@1.7.0"},"#,
+        r#"{"name":"serde","purl":"pkg:cargo/serde@1.0.0"},"not an object",{"name":"unknown"}],"#,
+        r#""rules":[{"allow":["c"],"priority":-1},{"allow":[],"priority":9},{"allow":["a","b"],"priority":10},{"allow":["a"],"priority":10}]}"#,
+    );
+
+    assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), expected);
+}
+
+
+#[test]
+fn exclusion()
+{
+    #[derive(Serialize)]
+    struct Meta
+    {
+        generated_at: u64,
+        generator: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct Signed
+    {
+        payload: Vec<serde_json::Value>,
+        _meta: Meta,
+        signature: &'static str,
+    }
+
+    let signed = Signed
+    {
+        payload: vec![serde_json::json!({ "id": 1, "unsigned": { "age": 3 } }), serde_json::json!({ "id": 2 })],
+        _meta: Meta { generated_at: 1700000000, generator: "tool" },
+        signature: "c2lnbmF0dXJl",
+    };
+
+    let formatter = CanonicalFormatter::new()
+        .with_exclusion("/signature").unwrap()
+        .with_exclusion("/_meta/generated_at").unwrap()
+        .with_exclusion("/payload/*/unsigned").unwrap();
+
+    let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    signed.serialize(&mut ser).unwrap();
+
+    assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), r#"{"_meta":{"generator":"tool"},"payload":[{"id":1},{"id":2}]}"#);
+
+    // Excluding every member leaves an empty object, and the exclusions apply to values too
+    let value = serde_json::json!({ "a~b": { "c/d": 1 }, "e": [{ "c/d": 2 }] });
+    let formatter = CanonicalFormatter::with_profile(Profile::Ssb).with_exclusion("/a~0b/c~1d").unwrap().with_exclusion("/*/0/c~1d").unwrap();
+
+    let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    value.serialize(&mut ser).unwrap();
+
+    assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), "{\n  \"a~b\": {},\n  \"e\": [\n    {}\n  ]\n}");
+}
+
+
+#[test]
+fn elision()
+{
+    #[derive(Serialize)]
+    struct Inner
+    {
+        note: Option<&'static str>,
+        tags: Vec<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct Outer
+    {
+        id: u32,
+        inner: Inner,
+        list: Vec<Option<u32>>,
+        extra: HashMap<&'static str, Inner>,
+    }
+
+    let outer = Outer
+    {
+        id: 1,
+        inner: Inner { note: None, tags: vec![] },
+        list: vec![None, Some(2)],
+        extra: HashMap::from([("x", Inner { note: Some("n"), tags: vec![] })]),
+    };
+
+    let serialize = |formatter: CanonicalFormatter|
+    {
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        outer.serialize(&mut ser).unwrap();
+
+        String::from_utf8(ser.into_inner()).unwrap()
+    };
+
+    assert_eq!(serialize(CanonicalFormatter::new().with_elision(ElisionPolicy::Null)), r#"{"extra":{"x":{"note":"n","tags":[]}},"id":1,"inner":{"tags":[]},"list":[null,2]}"#);
+
+    // The emptied inner object goes too, while array elements stay
+    assert_eq!(serialize(CanonicalFormatter::new().with_elision(ElisionPolicy::NullAndEmpty)), r#"{"extra":{"x":{"note":"n"}},"id":1,"list":[null,2]}"#);
+
+    assert_eq!(serialize(CanonicalFormatter::with_profile(Profile::Ssb).with_elision(ElisionPolicy::NullAndEmpty)), "{\n  \"id\": 1,\n  \"list\": [\n    null,\n    2\n  ],\n  \"extra\": {\n    \"x\": {\n      \"note\": \"n\"\n    }\n  }\n}");
+}
+
+
+#[test]
+fn limits()
+{
+    let check = |value: serde_json::Value, limits: Limits| -> Result<String, CanonicalError>
+    {
+        let mut ser = Serializer::with_formatter(Vec::new(), CanonicalFormatter::new().with_limits(limits));
+
+        match value.serialize(&mut ser)
+        {
+            Ok(()) => Ok(String::from_utf8(ser.into_inner()).unwrap()),
+            Err(error) => Err(std::io::Error::from(error).get_ref().unwrap().downcast_ref::<CanonicalError>().cloned().unwrap()),
+        }
+    };
+
+    let depth = Limits { depth: Some(3), ..Limits::default() };
+
+    assert_eq!(check(serde_json::json!({ "a": [{ "b": 1 }] }), depth), Ok(r#"{"a":[{"b":1}]}"#.to_owned()));
+    assert_eq!(check(serde_json::json!({ "a": [[[]]] }), depth), Err(CanonicalError::TooDeep(3)));
+
+    let members = Limits { members: Some(2), ..Limits::default() };
+
+    assert!(check(serde_json::json!({ "a": 1, "b": { "c": 2, "d": 3 } }), members).is_ok());
+    assert_eq!(check(serde_json::json!({ "a": 1, "b": 2, "c": 3 }), members), Err(CanonicalError::TooManyMembers(2)));
+
+    // Keys count as strings, and escapes as one byte each
+    let string_length = Limits { string_length: Some(4), ..Limits::default() };
+
+    assert!(check(serde_json::json!({ "\"é\"": "ab\\c" }), string_length).is_ok());
+    assert_eq!(check(serde_json::json!(["abcde"]), string_length), Err(CanonicalError::StringTooLong(4)));
+    assert_eq!(check(serde_json::json!({ "abc\n\n": 1 }), string_length), Err(CanonicalError::StringTooLong(4)));
+
+    // {"a":[1,2],"b":"xyz"} buffers 16 bytes of keys and values
+    let buffered_bytes = Limits { buffered_bytes: Some(16), ..Limits::default() };
+
+    assert!(check(serde_json::json!({ "a": [1, 2], "b": "xyz" }), buffered_bytes).is_ok());
+    assert_eq!(check(serde_json::json!({ "a": [1, 2], "b": "wxyz" }), buffered_bytes), Err(CanonicalError::TooLarge(16)));
+    assert!(check(serde_json::json!(["a very long string that is never buffered"]), buffered_bytes).is_ok());
+
+    // Canonicalizing text fails at the limit instead of reading on
+    let formatter = CanonicalFormatter::new().with_limits(Limits { depth: Some(64), ..Limits::default() });
+    let error = crate::canonicalize_str(&"[".repeat(1_000_000), formatter).unwrap_err();
+
+    assert_eq!(error.get_ref().unwrap().downcast_ref::<CanonicalError>(), Some(&CanonicalError::TooDeep(64)));
+}
@@ -0,0 +1,757 @@
+//! Canonicalization of JSON text, driving a [CanonicalFormatter] straight from the input tokens.
+
+use std::io;
+use serde_json::ser::{CharEscape, Formatter};
+use crate::{CanonicalError, CanonicalFormatter, SurrogatePolicy};
+
+
+/// Canonicalizes JSON text with the rules of `formatter`.
+///
+/// Lone surrogates in `\u` escapes are handled by the formatter's [SurrogatePolicy].
+pub fn canonicalize_str(json: &str, formatter: CanonicalFormatter) -> io::Result<Vec<u8>>
+{
+    canonicalize_slice(json.as_bytes(), formatter)
+}
+
+
+/// Canonicalizes JSON text given as bytes, which must be UTF-8.
+pub fn canonicalize_slice(json: &[u8], mut formatter: CanonicalFormatter) -> io::Result<Vec<u8>>
+{
+    let mut output = Vec::with_capacity(json.len());
+    let mut parser = Parser::new();
+
+    parser.feed(json, &mut formatter, &mut output)?;
+    parser.finish(&mut formatter, &mut output)?;
+
+    Ok(output)
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Container
+{
+    Object,
+    Array,
+}
+
+
+/// What the parser accepts next, besides whitespace.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Expect
+{
+    Value,
+    /// A value or `]`, right after `[`.
+    FirstElement,
+    /// A key or `}`, right after `{`.
+    FirstKey,
+    Key,
+    Colon,
+    CommaOrEnd,
+    Done,
+}
+
+
+/// A token that may continue in the next chunk.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Token
+{
+    String { key: bool },
+    Number,
+    Literal,
+}
+
+
+/// A decoded run of a string, split where a lone surrogate is kept as an escape.
+enum Piece
+{
+    Text(String),
+    Surrogate(u16),
+}
+
+
+/// Parses JSON text handed over in chunks of any size, driving a [CanonicalFormatter] as each token
+/// completes.
+///
+/// Only a token that straddles two chunks is copied; containers are buffered by the formatter.
+pub(crate) struct Parser
+{
+    stack: Vec<Container>,
+    expect: Expect,
+    token: Option<Token>,
+    /// The start of a token that straddles chunks.
+    pending: Vec<u8>,
+    /// Whether the pending string ends with an unfinished escape.
+    escaped: bool,
+    /// The offset of the pending token, or of the next chunk.
+    token_offset: usize,
+    offset: usize,
+}
+
+
+impl Parser
+{
+    pub(crate) fn new() -> Self
+    {
+        Self { stack: Vec::new(), expect: Expect::Value, token: None, pending: Vec::new(), escaped: false, token_offset: 0, offset: 0 }
+    }
+
+
+    /// Parses the next chunk of input.
+    pub(crate) fn feed<W>(&mut self, chunk: &[u8], formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        let base = self.offset;
+        let mut index = 0;
+
+        while index < chunk.len()
+        {
+            if let Some(token) = self.token
+            {
+                index = self.continue_token(token, chunk, index, formatter, writer)?;
+                continue
+            }
+
+            let byte = chunk[index];
+            let offset = base + index;
+
+            match (self.expect, byte)
+            {
+                (_, b' ' | b'\t' | b'\n' | b'\r') => (),
+                (Expect::Value | Expect::FirstElement, b'{') =>
+                {
+                    self.begin_value(formatter, writer)?;
+                    formatter.begin_object(writer)?;
+                    self.stack.push(Container::Object);
+                    self.expect = Expect::FirstKey;
+                }
+                (Expect::Value | Expect::FirstElement, b'[') =>
+                {
+                    self.begin_value(formatter, writer)?;
+                    formatter.begin_array(writer)?;
+                    self.stack.push(Container::Array);
+                    self.expect = Expect::FirstElement;
+                }
+                (Expect::FirstElement, b']') | (Expect::CommaOrEnd, b']') if self.stack.last() == Some(&Container::Array) =>
+                {
+                    self.stack.pop();
+                    formatter.end_array(writer)?;
+                    self.end_value(formatter, writer)?;
+                }
+                (Expect::FirstKey, b'}') | (Expect::CommaOrEnd, b'}') if self.stack.last() == Some(&Container::Object) =>
+                {
+                    self.stack.pop();
+                    formatter.end_object(writer)?;
+                    self.end_value(formatter, writer)?;
+                }
+                (Expect::Value | Expect::FirstElement, b'"' | b'-' | b'0'..=b'9' | b'a'..=b'z') =>
+                {
+                    self.begin_value(formatter, writer)?;
+
+                    let token = match byte
+                    {
+                        b'"' => Token::String { key: false },
+                        b'a'..=b'z' => Token::Literal,
+                        _ => Token::Number,
+                    };
+
+                    index = self.start_token(token, chunk, index, formatter, writer)?;
+                    continue
+                }
+                (Expect::FirstKey | Expect::Key, b'"') =>
+                {
+                    formatter.begin_object_key(writer, self.expect == Expect::FirstKey)?;
+
+                    index = self.start_token(Token::String { key: true }, chunk, index, formatter, writer)?;
+                    continue
+                }
+                (Expect::Colon, b':') =>
+                {
+                    formatter.begin_object_value(writer)?;
+                    self.expect = Expect::Value;
+                }
+                (Expect::CommaOrEnd, b',') =>
+                {
+                    self.expect = match self.stack.last()
+                    {
+                        Some(Container::Array) =>
+                        {
+                            formatter.begin_array_value(writer, false)?;
+                            Expect::Value
+                        }
+                        _ => Expect::Key,
+                    };
+                }
+                (Expect::Value | Expect::FirstElement, _) => return Err(error(offset, "Expected a value")),
+                (Expect::FirstKey | Expect::Key, _) => return Err(error(offset, "Expected a string key")),
+                (Expect::Colon, _) => return Err(error(offset, "Expected ':'")),
+                (Expect::CommaOrEnd, _) if self.stack.last() == Some(&Container::Object) => return Err(error(offset, "Expected ',' or '}'")),
+                (Expect::CommaOrEnd, _) => return Err(error(offset, "Expected ',' or ']'")),
+                (Expect::Done, _) => return Err(error(offset, "Unexpected characters after the value")),
+            }
+
+            index += 1;
+        }
+
+        self.offset = base + chunk.len();
+
+        Ok(())
+    }
+
+
+    /// Ends the input, which must have held exactly one value.
+    pub(crate) fn finish<W>(&mut self, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        // Only a number or literal can end with the input
+        if let Some(token @ (Token::Number | Token::Literal)) = self.token
+        {
+            let pending = std::mem::take(&mut self.pending);
+            self.complete_token(token, &pending, self.token_offset, formatter, writer)?;
+        }
+
+        match (self.expect, self.token)
+        {
+            (Expect::Done, None) => Ok(()),
+            (_, Some(Token::String { .. })) => Err(error(self.offset, "Unterminated string")),
+            _ => Err(error(self.offset, "Unexpected end of input")),
+        }
+    }
+
+
+    fn begin_value<W>(&mut self, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        if self.expect == Expect::FirstElement
+        {
+            formatter.begin_array_value(writer, true)?;
+        }
+
+        Ok(())
+    }
+
+
+    fn end_value<W>(&mut self, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        self.expect = match self.stack.last()
+        {
+            Some(Container::Object) =>
+            {
+                formatter.end_object_value(writer)?;
+                Expect::CommaOrEnd
+            }
+            Some(Container::Array) =>
+            {
+                formatter.end_array_value(writer)?;
+                Expect::CommaOrEnd
+            }
+            None => Expect::Done,
+        };
+
+        Ok(())
+    }
+
+
+    /// Starts a token at `start`, returning where parsing goes on.
+    fn start_token<W>(&mut self, token: Token, chunk: &[u8], start: usize, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<usize>
+        where
+            W: ?Sized + io::Write,
+    {
+        self.token = Some(token);
+        self.token_offset = self.offset + start;
+        self.pending.clear();
+        self.pending.push(chunk[start]);
+        self.escaped = false;
+
+        let end = token_end(token, &self.pending, &chunk[start + 1..], &mut self.escaped);
+
+        // Tokens that end within the chunk are read from it in place
+        if let Some(length) = end
+        {
+            let end = start + 1 + length;
+            self.complete_token(token, &chunk[start..end], self.token_offset, formatter, writer)?;
+            return Ok(end)
+        }
+
+        self.pending.extend_from_slice(&chunk[start + 1..]);
+
+        Ok(chunk.len())
+    }
+
+
+    /// Carries on with the pending token from `start`, returning where parsing goes on.
+    fn continue_token<W>(&mut self, token: Token, chunk: &[u8], start: usize, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<usize>
+        where
+            W: ?Sized + io::Write,
+    {
+        let rest = &chunk[start..];
+
+        let Some(length) = token_end(token, &self.pending, rest, &mut self.escaped) else
+        {
+            self.pending.extend_from_slice(rest);
+            return Ok(chunk.len())
+        };
+
+        self.pending.extend_from_slice(&rest[..length]);
+
+        let pending = std::mem::take(&mut self.pending);
+        self.complete_token(token, &pending, self.token_offset, formatter, writer)?;
+        self.pending = pending;
+
+        Ok(start + length)
+    }
+
+
+    /// Writes a whole token, which starts at `offset` in the input.
+    fn complete_token<W>(&mut self, token: Token, bytes: &[u8], offset: usize, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        self.token = None;
+
+        let mut tokenizer = Tokenizer { input: bytes, position: 0, base: offset };
+
+        match token
+        {
+            Token::String { key } =>
+            {
+                tokenizer.position = 1;
+                tokenizer.string(formatter, writer)?;
+
+                if key
+                {
+                    formatter.end_object_key(writer)?;
+                    self.expect = Expect::Colon;
+
+                    return Ok(())
+                }
+            }
+            Token::Number =>
+            {
+                tokenizer.number(formatter, writer)?;
+
+                if tokenizer.position < bytes.len()
+                {
+                    return Err(error(offset + tokenizer.position, "Invalid number"))
+                }
+            }
+            Token::Literal => match bytes
+            {
+                b"true" => formatter.write_bool(writer, true)?,
+                b"false" => formatter.write_bool(writer, false)?,
+                b"null" => formatter.write_null(writer)?,
+                _ => return Err(error(offset, "Expected a value")),
+            },
+        }
+
+        self.end_value(formatter, writer)
+    }
+}
+
+
+/// Finds how much of `rest` belongs to a token that started with `pending`, if it ends there.
+fn token_end(token: Token, pending: &[u8], rest: &[u8], escaped: &mut bool) -> Option<usize>
+{
+    match token
+    {
+        Token::String { .. } =>
+        {
+            for (index, byte) in rest.iter().enumerate()
+            {
+                match (*escaped, byte)
+                {
+                    (true, _) => *escaped = false,
+                    (false, b'\\') => *escaped = true,
+                    (false, b'"') => return Some(index + 1),
+                    _ => (),
+                }
+            }
+
+            None
+        }
+        Token::Number => rest.iter().position(|byte| !is_number_byte(*byte)),
+        Token::Literal =>
+        {
+            // A literal ends after its last letter, so `truex` fails after `true` as it would in serde_json
+            let length: usize = match pending.first()
+            {
+                Some(b't' | b'n') => 4,
+                Some(b'f') => 5,
+                _ => 1,
+            };
+
+            let needed = length.saturating_sub(pending.len());
+            let letters = rest.iter().take(needed).position(|byte| !byte.is_ascii_lowercase());
+
+            letters.or((rest.len() >= needed).then_some(needed))
+        }
+    }
+}
+
+
+fn is_number_byte(byte: u8) -> bool
+{
+    matches!(byte, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')
+}
+
+
+fn error(offset: usize, reason: &'static str) -> io::Error
+{
+    CanonicalError::InvalidJson { offset, reason }.into()
+}
+
+
+/// Decodes the string whose opening quote is at `start`, rejecting lone surrogates, and returns it
+/// with the offset after its closing quote.
+pub(crate) fn decode_string(input: &[u8], start: usize) -> io::Result<(String, usize)>
+{
+    let mut tokenizer = Tokenizer { input, position: start + 1, base: 0 };
+
+    // Rejected surrogates leave nothing but text
+    let text = tokenizer.decode_string(SurrogatePolicy::Reject)?
+        .into_iter()
+        .filter_map(|piece| match piece
+        {
+            Piece::Text(text) => Some(text),
+            Piece::Surrogate(_) => None,
+        })
+        .collect();
+
+    Ok((text, tokenizer.position))
+}
+
+
+/// Decodes and writes single tokens.
+struct Tokenizer<'a>
+{
+    input: &'a [u8],
+    position: usize,
+    /// The offset of the input within the whole text, for errors.
+    base: usize,
+}
+
+
+impl Tokenizer<'_>
+{
+    /// Reads the rest of a string whose opening quote was consumed, and writes it.
+    fn string<W>(&mut self, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        let pieces = self.decode_string(formatter.surrogate_policy())?;
+
+        formatter.begin_string(writer)?;
+
+        for piece in pieces
+        {
+            match piece
+            {
+                Piece::Text(text) => write_escaped(formatter, writer, &text)?,
+                Piece::Surrogate(unit) => formatter.write_surrogate_escape(writer, unit)?,
+            }
+        }
+
+        formatter.end_string(writer)
+    }
+
+
+    fn decode_string(&mut self, policy: SurrogatePolicy) -> io::Result<Vec<Piece>>
+    {
+        let mut pieces = Vec::new();
+        let mut text = Vec::new();
+
+        loop
+        {
+            let start = self.position;
+
+            let Some(byte) = self.next_byte() else
+            {
+                return Err(self.error("Unterminated string"))
+            };
+
+            match byte
+            {
+                b'"' => break,
+                b'\\' => (),
+                0x00..=0x1F =>
+                {
+                    self.position = start;
+                    return Err(self.error("Control character in string"))
+                }
+                _ =>
+                {
+                    text.push(byte);
+                    continue
+                }
+            }
+
+            let escaped = match self.next_byte()
+            {
+                Some(b'"') => b'"',
+                Some(b'\\') => b'\\',
+                Some(b'/') => b'/',
+                Some(b'b') => 0x08,
+                Some(b'f') => 0x0C,
+                Some(b'n') => b'\n',
+                Some(b'r') => b'\r',
+                Some(b't') => b'\t',
+                Some(b'u') =>
+                {
+                    let unit = self.hex_unit(start)?;
+
+                    let code_point = match unit
+                    {
+                        0xD800..=0xDBFF if self.input[self.position..].starts_with(b"\\u") =>
+                        {
+                            let resume = self.position;
+                            self.position += 2;
+                            let low = self.hex_unit(resume)?;
+
+                            if (0xDC00..0xE000).contains(&low)
+                            {
+                                Some(0x10000 + ((unit as u32 - 0xD800) << 10) + (low as u32 - 0xDC00))
+                            }
+                            else
+                            {
+                                // The next escape stands on its own
+                                self.position = resume;
+                                None
+                            }
+                        }
+                        0xD800..=0xDFFF => None,
+                        _ => Some(unit as u32),
+                    };
+
+                    match (code_point.and_then(char::from_u32), policy)
+                    {
+                        (Some(ch), _) => text.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+                        (None, SurrogatePolicy::Reject) => return Err(CanonicalError::LoneSurrogate(unit).into()),
+                        (None, SurrogatePolicy::Replace) => text.extend_from_slice("\u{FFFD}".as_bytes()),
+                        (None, SurrogatePolicy::Preserve) =>
+                        {
+                            pieces.push(Piece::Text(self.utf8(std::mem::take(&mut text), start)?));
+                            pieces.push(Piece::Surrogate(unit));
+                        }
+                    }
+
+                    continue
+                }
+                _ =>
+                {
+                    self.position = start;
+                    return Err(self.error("Invalid escape"))
+                }
+            };
+
+            text.push(escaped);
+        }
+
+        pieces.push(Piece::Text(self.utf8(text, self.position)?));
+
+        Ok(pieces)
+    }
+
+
+    /// Reads the four hex digits of a `\u` escape that started at `start`.
+    fn hex_unit(&mut self, start: usize) -> io::Result<u16>
+    {
+        let digits = self.input.get(self.position..self.position + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()));
+
+        let Some(digits) = digits else
+        {
+            self.position = start;
+            return Err(self.error("Invalid unicode escape"))
+        };
+
+        self.position += 4;
+
+        Ok(u16::from_str_radix(digits, 16).unwrap_or_default())
+    }
+
+
+    fn number<W>(&mut self, formatter: &mut CanonicalFormatter, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+    {
+        let start = self.position;
+
+        self.skip_byte(b'-');
+
+        match self.next_byte()
+        {
+            Some(b'0') => (),
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(self.error_before("Invalid number")),
+        }
+
+        let mut integer = true;
+
+        if self.skip_byte(b'.')
+        {
+            integer = false;
+            self.required_digits()?;
+        }
+
+        if self.skip_byte(b'e') || self.skip_byte(b'E')
+        {
+            integer = false;
+            let _ = self.skip_byte(b'+') || self.skip_byte(b'-');
+            self.required_digits()?;
+        }
+
+        // Only ASCII was consumed
+        let text = std::str::from_utf8(&self.input[start..self.position]).unwrap_or_default();
+
+        if !integer
+        {
+            return match text.parse::<f64>()
+            {
+                Ok(value) if value.is_finite() => formatter.write_f64(writer, value),
+                _ =>
+                {
+                    self.position = start;
+                    Err(self.error("Number is out of range"))
+                }
+            }
+        }
+
+        if let Ok(value) = text.parse::<u64>()
+        {
+            formatter.write_u64(writer, value)
+        }
+        else if let Ok(value) = text.parse::<i64>()
+        {
+            formatter.write_i64(writer, value)
+        }
+        else if let Ok(value) = text.parse::<u128>()
+        {
+            formatter.write_u128(writer, value)
+        }
+        else if let Ok(value) = text.parse::<i128>()
+        {
+            formatter.write_i128(writer, value)
+        }
+        else
+        {
+            formatter.write_number_str(writer, text)
+        }
+    }
+
+
+    fn utf8(&self, bytes: Vec<u8>, position: usize) -> io::Result<String>
+    {
+        String::from_utf8(bytes).map_err(|_| CanonicalError::InvalidJson { offset: self.base + position, reason: "String is not valid UTF-8" }.into())
+    }
+
+
+    fn next_byte(&mut self) -> Option<u8>
+    {
+        let byte = self.input.get(self.position).copied();
+
+        if byte.is_some()
+        {
+            self.position += 1;
+        }
+
+        byte
+    }
+
+
+    fn skip_byte(&mut self, expected: u8) -> bool
+    {
+        let found = self.input.get(self.position) == Some(&expected);
+
+        if found
+        {
+            self.position += 1;
+        }
+
+        found
+    }
+
+
+    fn skip_digits(&mut self)
+    {
+        while self.input.get(self.position).is_some_and(u8::is_ascii_digit)
+        {
+            self.position += 1;
+        }
+    }
+
+
+    fn required_digits(&mut self) -> io::Result<()>
+    {
+        if !self.input.get(self.position).is_some_and(u8::is_ascii_digit)
+        {
+            return Err(self.error("Invalid number"))
+        }
+
+        self.skip_digits();
+
+        Ok(())
+    }
+
+
+    fn error(&self, reason: &'static str) -> io::Error
+    {
+        CanonicalError::InvalidJson { offset: self.base + self.position, reason }.into()
+    }
+
+
+    /// An error about the byte that was just consumed.
+    fn error_before(&self, reason: &'static str) -> io::Error
+    {
+        CanonicalError::InvalidJson { offset: self.base + self.position.saturating_sub(1).min(self.input.len()), reason }.into()
+    }
+}
+
+
+/// Writes string contents, handing the characters that JSON requires escaping to the formatter as
+/// escapes, the way serde_json does.
+fn write_escaped<W>(formatter: &mut CanonicalFormatter, writer: &mut W, text: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+{
+    let mut start = 0;
+
+    for (index, byte) in text.bytes().enumerate()
+    {
+        let escape = match byte
+        {
+            b'"' => CharEscape::Quote,
+            b'\\' => CharEscape::ReverseSolidus,
+            0x08 => CharEscape::Backspace,
+            0x0C => CharEscape::FormFeed,
+            b'\n' => CharEscape::LineFeed,
+            b'\r' => CharEscape::CarriageReturn,
+            b'\t' => CharEscape::Tab,
+            0x00..=0x1F => CharEscape::AsciiControl(byte),
+            _ => continue,
+        };
+
+        if start < index
+        {
+            formatter.write_string_fragment(writer, &text[start..index])?;
+        }
+
+        formatter.write_char_escape(writer, escape)?;
+        start = index + 1;
+    }
+
+    if start < text.len()
+    {
+        formatter.write_string_fragment(writer, &text[start..])?;
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests;
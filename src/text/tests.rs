@@ -0,0 +1,119 @@
+use crate::{CanonicalError, CanonicalFormatter, EscapePolicy, IJsonPolicy, Profile, SurrogatePolicy, canonicalize_str};
+
+
+fn canonicalize(json: &str, policy: SurrogatePolicy) -> std::io::Result<String>
+{
+    let bytes = canonicalize_str(json, CanonicalFormatter::new().with_surrogate_policy(policy))?;
+
+    Ok(String::from_utf8(bytes).unwrap())
+}
+
+
+#[test]
+fn matches_serialized_values()
+{
+    let json = r#" { "c" : [ 1, -9223372036854775808, 18446744073709551615, true, false, null ], "b" : { }, "a" : [ ], "\u00e9\n\"" : "x\ty\/z\u0001" } "#;
+
+    for formatter in [CanonicalFormatter::new, || CanonicalFormatter::with_profile(Profile::JavaScript), || CanonicalFormatter::new().with_escape_policy(EscapePolicy::AsciiOnly)]
+    {
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+
+        let mut ser = serde_json::Serializer::with_formatter(Vec::new(), formatter());
+        serde::Serialize::serialize(&value, &mut ser).unwrap();
+
+        assert_eq!(canonicalize_str(json, formatter()).unwrap(), ser.into_inner());
+    }
+
+    let floats = canonicalize_str("[3.5e0, 1E21, -0.0, 0.000001]", CanonicalFormatter::with_profile(Profile::JavaScript)).unwrap();
+    assert_eq!(floats, b"[3.5,1e+21,0,0.000001]");
+}
+
+
+#[test]
+fn surrogate_pairs()
+{
+    for policy in [SurrogatePolicy::Reject, SurrogatePolicy::Replace, SurrogatePolicy::Preserve]
+    {
+        assert_eq!(canonicalize(r#"["\ud83d\ude00"]"#, policy).unwrap(), "[\"\u{1F600}\"]");
+    }
+}
+
+
+#[test]
+fn reject_lone_surrogates()
+{
+    for json in [r#""\ud800""#, r#""a\udc00b""#, r#"{"\ud83d\u0041":1}"#, r#""\ude00\ud83d""#]
+    {
+        let error = canonicalize(json, SurrogatePolicy::Reject).unwrap_err();
+
+        assert!(matches!(CanonicalError::from_io(&error), Some(CanonicalError::LoneSurrogate(0xD800..=0xDFFF))), "{}", json);
+    }
+
+    let error = canonicalize(r#""\ud83d\u0041""#, SurrogatePolicy::Reject).unwrap_err();
+    assert_eq!(CanonicalError::from_io(&error), Some(&CanonicalError::LoneSurrogate(0xD83D)));
+}
+
+
+#[test]
+fn replace_lone_surrogates()
+{
+    assert_eq!(canonicalize(r#""a\ud800b""#, SurrogatePolicy::Replace).unwrap(), "\"a\u{FFFD}b\"");
+    assert_eq!(canonicalize(r#""\ud83d\u0041\udc00""#, SurrogatePolicy::Replace).unwrap(), "\"\u{FFFD}A\u{FFFD}\"");
+    assert_eq!(canonicalize(r#"{"\uDFFF":["\uD800"]}"#, SurrogatePolicy::Replace).unwrap(), "{\"\u{FFFD}\":[\"\u{FFFD}\"]}");
+}
+
+
+#[test]
+fn preserve_lone_surrogates()
+{
+    assert_eq!(canonicalize(r#""a\uD800b""#, SurrogatePolicy::Preserve).unwrap(), r#""a\ud800b""#);
+    assert_eq!(canonicalize(r#""\ude00\ud83d\n""#, SurrogatePolicy::Preserve).unwrap(), "\"\\ude00\\ud83d\n\"");
+
+    // Keys that only differ in their lone surrogates still have a single order
+    let forward = canonicalize(r#"{"\udc01":1,"\udc00":2,"b":3}"#, SurrogatePolicy::Preserve).unwrap();
+    let backward = canonicalize(r#"{"b":3,"\udc00":2,"\udc01":1}"#, SurrogatePolicy::Preserve).unwrap();
+
+    assert_eq!(forward, r#"{"b":3,"\udc00":2,"\udc01":1}"#);
+    assert_eq!(forward, backward);
+
+    // I-JSON forbids them whatever the policy
+    let formatter = CanonicalFormatter::new().with_surrogate_policy(SurrogatePolicy::Preserve).with_ijson(IJsonPolicy::Strict);
+    let error = canonicalize_str(r#""\ud800""#, formatter).unwrap_err();
+
+    assert_eq!(CanonicalError::from_io(&error), Some(&CanonicalError::LoneSurrogate(0xD800)));
+}
+
+
+#[test]
+fn invalid_json()
+{
+    for (json, offset) in [("", 0), ("[1,]", 3), ("{\"a\" 1}", 5), ("[01]", 2), ("\"\\x\"", 1), ("\"a", 2), ("1 2", 2), ("[1e400]", 1), ("tru", 0)]
+    {
+        let error = canonicalize(json, SurrogatePolicy::Reject).unwrap_err();
+
+        let Some(&CanonicalError::InvalidJson { offset: found, .. }) = CanonicalError::from_io(&error) else
+        {
+            panic!("{} was not reported as invalid JSON", json)
+        };
+
+        assert_eq!(found, offset, "{}", json);
+    }
+}
+
+
+#[test]
+fn deep_nesting()
+{
+    let json = format!("{}0{}", "[{\"a\":".repeat(10_000), "}]".repeat(10_000));
+
+    assert_eq!(canonicalize(&json, SurrogatePolicy::Reject).unwrap(), json);
+}
+
+
+#[test]
+fn wide_integers()
+{
+    let json = "[123456789012345678901234567890123456789012345,-340282366920938463463374607431768211456]";
+
+    assert_eq!(canonicalize(json, SurrogatePolicy::Reject).unwrap(), json);
+}
@@ -0,0 +1,322 @@
+//! # Data Integrity `eddsa-jcs-2022`
+//!
+//! Creates and verifies [Data Integrity](https://www.w3.org/TR/vc-data-integrity/) proofs using the
+//! [`eddsa-jcs-2022`](https://www.w3.org/TR/vc-di-eddsa/#eddsa-jcs-2022) cryptosuite.
+//!
+//! Both the proof configuration and the unsecured document are canonicalized with the JSON
+//! Canonicalization Scheme of [Profile::Jcs](crate::Profile::Jcs), hashed with SHA-256, and the
+//! concatenated hashes are signed with Ed25519. The signature is stored in the proof as a base58-btc
+//! multibase string.
+//!
+//! ```rust
+//! use ed25519_dalek::SigningKey;
+//! use serde_json::json;
+//! use serde_canonical_json::vc::{self, DataIntegrityProof};
+//!
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//! let verification_method = format!("did:key:{0}#{0}", vc::public_key_multibase(&key.verifying_key()));
+//!
+//! let document = json!({ "id": "urn:example:1", "name": "Example" });
+//! let options = DataIntegrityProof::new(verification_method, "assertionMethod");
+//!
+//! let secured = vc::add_proof(&document, &options, &key).unwrap();
+//!
+//! vc::verify_proof(&secured, &key.verifying_key()).unwrap();
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use crate::{CanonicalFormatter, Profile, to_vec_with_formatter};
+
+
+/// The `type` of every proof produced by this module.
+pub const PROOF_TYPE: &str = "DataIntegrityProof";
+
+/// The `cryptosuite` identifier of every proof produced by this module.
+pub const CRYPTOSUITE: &str = "eddsa-jcs-2022";
+
+const ED25519_PUB_HEADER: [u8; 2] = [0xed, 0x01];
+const ED25519_PRIV_HEADER: [u8; 2] = [0x80, 0x26];
+
+
+/// A `DataIntegrityProof` object.
+///
+/// Without a `proof_value` this doubles as the proof options passed to [create_proof].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof
+{
+    #[serde(rename = "@context", skip_serializing_if = "Option::is_none")]
+    pub context: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub proof_type: String,
+    pub cryptosuite: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    pub verification_method: String,
+    pub proof_purpose: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_proof: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_value: Option<String>,
+}
+
+
+impl DataIntegrityProof
+{
+    /// Creates proof options for the `eddsa-jcs-2022` cryptosuite with no optional members set.
+    pub fn new(verification_method: impl Into<String>, proof_purpose: impl Into<String>) -> Self
+    {
+        Self
+        {
+            context: None,
+            id: None,
+            proof_type: PROOF_TYPE.to_owned(),
+            cryptosuite: CRYPTOSUITE.to_owned(),
+            created: None,
+            expires: None,
+            verification_method: verification_method.into(),
+            proof_purpose: proof_purpose.into(),
+            domain: None,
+            challenge: None,
+            nonce: None,
+            previous_proof: None,
+            proof_value: None,
+        }
+    }
+}
+
+
+/// Creates a proof over `unsecured_document` using the given proof options.
+///
+/// If the document has an `@context`, it is copied into the proof before the proof configuration is
+/// canonicalized, as the cryptosuite requires.
+pub fn create_proof<T>(unsecured_document: &T, options: &DataIntegrityProof, key: &SigningKey) -> io::Result<DataIntegrityProof>
+    where
+        T: ?Sized + Serialize,
+{
+    check_suite(&options.proof_type, &options.cryptosuite)?;
+
+    let document = serde_json::to_value(unsecured_document)?;
+
+    let Value::Object(document) = document else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Unsecured document must be an object."))
+    };
+
+    if document.contains_key("proof")
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Unsecured document must not contain a proof."))
+    }
+
+    let mut proof = options.clone();
+    proof.proof_value = None;
+
+    if let Some(context) = document.get("@context")
+    {
+        proof.context = Some(context.clone());
+    }
+
+    let hash_data = hash_data(&proof, &document)?;
+    let signature = key.sign(&hash_data);
+
+    proof.proof_value = Some(multibase_encode(&signature.to_bytes()));
+
+    Ok(proof)
+}
+
+
+/// Creates a proof over `unsecured_document` and returns the secured document with the proof attached.
+pub fn add_proof<T>(unsecured_document: &T, options: &DataIntegrityProof, key: &SigningKey) -> io::Result<Value>
+    where
+        T: ?Sized + Serialize,
+{
+    let proof = create_proof(unsecured_document, options, key)?;
+
+    let Value::Object(mut document) = serde_json::to_value(unsecured_document)? else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Unsecured document must be an object."))
+    };
+
+    document.insert("proof".to_owned(), serde_json::to_value(proof)?);
+
+    Ok(Value::Object(document))
+}
+
+
+/// Verifies the proof attached to `secured_document` with the given public key.
+///
+/// Returns the verified proof on success.
+pub fn verify_proof(secured_document: &Value, key: &VerifyingKey) -> io::Result<DataIntegrityProof>
+{
+    let Some(secured) = secured_document.as_object() else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Secured document must be an object."))
+    };
+
+    let mut unsecured = secured.clone();
+
+    let Some(proof_value) = unsecured.remove("proof") else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Secured document does not contain a proof."))
+    };
+
+    let Value::Object(mut proof_config) = proof_value else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Proof must be a single object."))
+    };
+
+    let proof: DataIntegrityProof = serde_json::from_value(Value::Object(proof_config.clone()))?;
+
+    check_suite(&proof.proof_type, &proof.cryptosuite)?;
+
+    if let Some(context) = &proof.context
+    {
+        if !context_starts_with(unsecured.get("@context"), context)
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Document context does not start with the proof context."))
+        }
+    }
+
+    let Some(encoded) = &proof.proof_value else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Proof does not contain a proof value."))
+    };
+
+    let signature = multibase_decode(encoded)?;
+    let Ok(signature) = Signature::from_slice(&signature) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Proof value is not an Ed25519 signature."))
+    };
+
+    // Verify over the received proof, so members this module does not know about are still covered
+    proof_config.remove("proofValue");
+
+    let hash_data = hash_data(&proof_config, &unsecured)?;
+
+    if key.verify_strict(&hash_data, &signature).is_err()
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Proof signature is invalid."))
+    }
+
+    Ok(proof)
+}
+
+
+/// Encodes an Ed25519 public key as a `publicKeyMultibase` value, as used by `did:key`.
+pub fn public_key_multibase(key: &VerifyingKey) -> String
+{
+    let mut bytes = ED25519_PUB_HEADER.to_vec();
+    bytes.extend_from_slice(key.as_bytes());
+
+    multibase_encode(&bytes)
+}
+
+
+/// Decodes a `publicKeyMultibase` value holding an Ed25519 public key.
+pub fn verifying_key_from_multibase(encoded: &str) -> io::Result<VerifyingKey>
+{
+    let bytes = multibase_decode(encoded)?;
+
+    let Some(key) = bytes.strip_prefix(&ED25519_PUB_HEADER).and_then(|key| <&[u8; 32]>::try_from(key).ok()) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Multibase value is not an Ed25519 public key."))
+    };
+
+    VerifyingKey::from_bytes(key).map_err(|_| Error::new(ErrorKind::InvalidData, "Multibase value is not an Ed25519 public key."))
+}
+
+
+/// Decodes a `secretKeyMultibase` value holding an Ed25519 secret key.
+pub fn signing_key_from_multibase(encoded: &str) -> io::Result<SigningKey>
+{
+    let bytes = multibase_decode(encoded)?;
+
+    let Some(key) = bytes.strip_prefix(&ED25519_PRIV_HEADER).and_then(|key| <&[u8; 32]>::try_from(key).ok()) else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Multibase value is not an Ed25519 secret key."))
+    };
+
+    Ok(SigningKey::from_bytes(key))
+}
+
+
+fn check_suite(proof_type: &str, cryptosuite: &str) -> io::Result<()>
+{
+    if proof_type != PROOF_TYPE
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Proof type must be DataIntegrityProof."))
+    }
+
+    if cryptosuite != CRYPTOSUITE
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Proof cryptosuite must be eddsa-jcs-2022."))
+    }
+
+    Ok(())
+}
+
+
+fn context_starts_with(document_context: Option<&Value>, proof_context: &Value) -> bool
+{
+    fn as_list(value: &Value) -> &[Value]
+    {
+        match value
+        {
+            Value::Array(values) => values,
+            value => std::slice::from_ref(value),
+        }
+    }
+
+    let Some(document_context) = document_context else
+    {
+        return false
+    };
+
+    as_list(document_context).starts_with(as_list(proof_context))
+}
+
+
+fn hash_data<P>(proof_config: &P, document: &Map<String, Value>) -> io::Result<Vec<u8>>
+    where
+        P: ?Sized + Serialize,
+{
+    let mut hash_data = Sha256::digest(to_vec_with_formatter(proof_config, CanonicalFormatter::with_profile(Profile::Jcs))?).to_vec();
+    hash_data.extend_from_slice(&Sha256::digest(to_vec_with_formatter(document, CanonicalFormatter::with_profile(Profile::Jcs))?));
+
+    Ok(hash_data)
+}
+
+
+fn multibase_encode(bytes: &[u8]) -> String
+{
+    format!("z{}", bs58::encode(bytes).into_string())
+}
+
+
+fn multibase_decode(encoded: &str) -> io::Result<Vec<u8>>
+{
+    let Some(encoded) = encoded.strip_prefix('z') else
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Multibase value must use base58-btc encoding."))
+    };
+
+    bs58::decode(encoded).into_vec().map_err(|_| Error::new(ErrorKind::InvalidData, "Multibase value is not valid base58-btc."))
+}
+
+
+#[cfg(test)]
+mod tests;
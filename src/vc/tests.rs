@@ -0,0 +1,125 @@
+use serde_json::{json, Value};
+use crate::vc::{self, DataIntegrityProof};
+
+
+// Test vectors from the eddsa-jcs-2022 section of the W3C Data Integrity EdDSA Cryptosuites specification
+
+const PUBLIC_KEY_MULTIBASE: &str = "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2";
+const SECRET_KEY_MULTIBASE: &str = "z3u2en7t5LR2WtQH5PfFqMqwVHBeXouLzo6haApm8XHqvjxq";
+const PROOF_VALUE: &str = "z2HnFSSPPBzR36zdDgK8PbEHeXbR56YF24jwMpt3R1eHXQzJDMWS93FCzpvJpwTWd3GAVFuUfjoJdcnTMuVor51aX";
+
+
+fn unsecured_document() -> Value
+{
+    json!({
+        "@context": [
+            "https://www.w3.org/ns/credentials/v2",
+            "https://www.w3.org/ns/credentials/examples/v2"
+        ],
+        "id": "urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33",
+        "type": ["VerifiableCredential", "AlumniCredential"],
+        "name": "Alumni Credential",
+        "description": "A minimum viable example of an Alumni Credential.",
+        "issuer": "https://vc.example/issuers/5678",
+        "validFrom": "2023-01-01T00:00:00Z",
+        "credentialSubject": {
+            "id": "did:example:abcdefgh",
+            "alumniOf": "The School of Examples"
+        }
+    })
+}
+
+
+fn proof_options() -> DataIntegrityProof
+{
+    let mut options = DataIntegrityProof::new(format!("did:key:{0}#{0}", PUBLIC_KEY_MULTIBASE), "assertionMethod");
+    options.created = Some("2023-02-24T23:36:38Z".to_owned());
+    options
+}
+
+
+#[test]
+fn key_vectors()
+{
+    let key = vc::signing_key_from_multibase(SECRET_KEY_MULTIBASE).unwrap();
+
+    assert_eq!(vc::public_key_multibase(&key.verifying_key()), PUBLIC_KEY_MULTIBASE);
+    assert_eq!(vc::verifying_key_from_multibase(PUBLIC_KEY_MULTIBASE).unwrap(), key.verifying_key());
+}
+
+
+#[test]
+fn create_proof_vector()
+{
+    let key = vc::signing_key_from_multibase(SECRET_KEY_MULTIBASE).unwrap();
+
+    let proof = vc::create_proof(&unsecured_document(), &proof_options(), &key).unwrap();
+
+    assert_eq!(proof.context, Some(unsecured_document()["@context"].clone()));
+    assert_eq!(proof.proof_value.as_deref(), Some(PROOF_VALUE));
+}
+
+
+#[test]
+fn verify_proof_vector()
+{
+    let key = vc::verifying_key_from_multibase(PUBLIC_KEY_MULTIBASE).unwrap();
+
+    let mut secured = unsecured_document();
+    secured["proof"] = json!({
+        "type": "DataIntegrityProof",
+        "cryptosuite": "eddsa-jcs-2022",
+        "created": "2023-02-24T23:36:38Z",
+        "verificationMethod": "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2#z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+        "proofPurpose": "assertionMethod",
+        "@context": [
+            "https://www.w3.org/ns/credentials/v2",
+            "https://www.w3.org/ns/credentials/examples/v2"
+        ],
+        "proofValue": PROOF_VALUE
+    });
+
+    let proof = vc::verify_proof(&secured, &key).unwrap();
+    assert_eq!(proof.proof_value.as_deref(), Some(PROOF_VALUE));
+
+    secured["name"] = json!("Forged Credential");
+    assert!(vc::verify_proof(&secured, &key).is_err());
+}
+
+
+#[test]
+fn verify_rejects_foreign_context()
+{
+    let key = vc::signing_key_from_multibase(SECRET_KEY_MULTIBASE).unwrap();
+
+    let mut secured = vc::add_proof(&unsecured_document(), &proof_options(), &key).unwrap();
+    secured["@context"] = json!(["https://www.w3.org/ns/credentials/v2"]);
+
+    assert!(vc::verify_proof(&secured, &key.verifying_key()).is_err());
+}
+
+
+#[test]
+fn hash_data_uses_jcs()
+{
+    use sha2::{Digest, Sha256};
+
+    // Canonical bytes from the RFC 8785 reference implementation, as in tests/fixtures/jcs.json
+    let document = json!({ "amount": 1.5, "note": "line\nbreak\ttab\u{1}", "😀": "non-BMP key", "ﬁ": "ligature key", "€": null });
+    let canonical = r#"{"amount":1.5,"note":"line\nbreak\ttab\u0001","€":null,"😀":"non-BMP key","ﬁ":"ligature key"}"#;
+
+    let Value::Object(map) = &document else
+    {
+        unreachable!()
+    };
+
+    let mut expected = Sha256::digest(r#"{"created":1e+21}"#).to_vec();
+    expected.extend_from_slice(&Sha256::digest(canonical));
+
+    assert_eq!(vc::hash_data(&json!({ "created": 1e21 }), map).unwrap(), expected);
+
+    let key = vc::signing_key_from_multibase(SECRET_KEY_MULTIBASE).unwrap();
+    let secured = vc::add_proof(&document, &proof_options(), &key).unwrap();
+
+    vc::verify_proof(&secured, &key.verifying_key()).unwrap();
+}
@@ -0,0 +1,111 @@
+//! # Canonicalizing writer
+//!
+//! An [io::Write] adapter for code that writes JSON text itself, rather than through serde.
+//!
+//! ```rust
+//! use std::io::Write;
+//! use serde_canonical_json::{CanonicalFormatter, CanonicalizingWriter};
+//!
+//! let mut writer = CanonicalizingWriter::new(Vec::new(), CanonicalFormatter::new());
+//!
+//! write!(writer, r#"{{ "b": [1, 2], "#).unwrap();
+//! write!(writer, r#""a": "x" }}"#).unwrap();
+//!
+//! assert_eq!(writer.finish().unwrap(), br#"{"a":"x","b":[1,2]}"#);
+//! ```
+
+use std::io::{self, ErrorKind, Error};
+use crate::CanonicalFormatter;
+use crate::text::Parser;
+
+
+/// Parses JSON text written to it in chunks of any size, and writes it canonically to `W`.
+///
+/// Writes always accept the whole chunk. The first error, whether in the JSON or from `W`, is
+/// returned by the next [flush](io::Write::flush) or by [finish](Self::finish), and the input after it
+/// is ignored.
+pub struct CanonicalizingWriter<W>
+{
+    inner: W,
+    formatter: CanonicalFormatter,
+    parser: Parser,
+    error: Option<Error>,
+    failed: bool,
+}
+
+
+impl<W> CanonicalizingWriter<W>
+    where
+        W: io::Write,
+{
+    /// Creates a writer that canonicalizes to `inner` with the rules of `formatter`.
+    pub fn new(inner: W, formatter: CanonicalFormatter) -> Self
+    {
+        Self { inner, formatter, parser: Parser::new(), error: None, failed: false }
+    }
+
+
+    /// Checks that a whole JSON value was written, and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W>
+    {
+        self.check()?;
+        self.parser.finish(&mut self.formatter, &mut self.inner)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+
+    pub fn get_ref(&self) -> &W
+    {
+        &self.inner
+    }
+
+
+    /// Returns the first error, or an error standing for it once it was returned.
+    fn check(&mut self) -> io::Result<()>
+    {
+        if let Some(error) = self.error.take()
+        {
+            return Err(error)
+        }
+
+        if self.failed
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "An earlier write failed."))
+        }
+
+        Ok(())
+    }
+}
+
+
+impl<W> io::Write for CanonicalizingWriter<W>
+    where
+        W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+        if !self.failed
+        {
+            if let Err(error) = self.parser.feed(buf, &mut self.formatter, &mut self.inner)
+            {
+                self.error = Some(error);
+                self.failed = true;
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.check()?;
+        self.inner.flush()
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
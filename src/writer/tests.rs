@@ -0,0 +1,80 @@
+use std::io::Write;
+use crate::{CanonicalError, CanonicalFormatter, CanonicalizingWriter, Profile, SurrogatePolicy, canonicalize_str};
+
+
+#[test]
+fn any_chunk_size()
+{
+    let json = r#" { "c" : [ 1, -12, 18446744073709551615, true, false, null, [ ] ], "b" : { "z" : "😀", "y" : { } }, "a" : "x\ty\/z\u0001\"\\" } "#;
+    let expected = canonicalize_str(json, CanonicalFormatter::new()).unwrap();
+
+    for size in 1..=json.len()
+    {
+        let mut writer = CanonicalizingWriter::new(Vec::new(), CanonicalFormatter::new());
+
+        for chunk in json.as_bytes().chunks(size)
+        {
+            writer.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(writer.finish().unwrap(), expected, "{}", size);
+    }
+}
+
+
+#[test]
+fn formatter_rules()
+{
+    let formatter = CanonicalFormatter::with_profile(Profile::JavaScript).with_surrogate_policy(SurrogatePolicy::Preserve);
+    let mut writer = CanonicalizingWriter::new(Vec::new(), formatter);
+
+    for chunk in [&b"[1."[..], b"50, 2e", b"1, \"\\ud8", b"00\", tr", b"ue]"]
+    {
+        writer.write_all(chunk).unwrap();
+    }
+
+    assert_eq!(writer.finish().unwrap(), br#"[1.5,20,"\ud800",true]"#);
+}
+
+
+#[test]
+fn scalars_at_the_end()
+{
+    for (json, expected) in [("12", Some("12")), ("null ", Some("null")), ("nul", None), ("-", None)]
+    {
+        let mut writer = CanonicalizingWriter::new(Vec::new(), CanonicalFormatter::new());
+        writer.write_all(json.as_bytes()).unwrap();
+
+        assert_eq!(writer.finish().ok(), expected.map(|expected| expected.as_bytes().to_vec()), "{}", json);
+    }
+}
+
+
+#[test]
+fn errors_on_flush()
+{
+    let mut writer = CanonicalizingWriter::new(Vec::new(), CanonicalFormatter::new());
+
+    writer.write_all(b"[1,").unwrap();
+    writer.write_all(b" 2 3]").unwrap();
+    writer.write_all(b"ignored").unwrap();
+
+    let error = writer.flush().unwrap_err();
+    assert_eq!(CanonicalError::from_io(&error), Some(&CanonicalError::InvalidJson { offset: 6, reason: "Expected ',' or ']'" }));
+
+    assert!(writer.flush().is_err());
+    assert!(writer.finish().is_err());
+}
+
+
+#[test]
+fn errors_on_finish()
+{
+    for json in [&b"{\"a\":1"[..], b"\"abc", b"", b"[1] [2]"]
+    {
+        let mut writer = CanonicalizingWriter::new(Vec::new(), CanonicalFormatter::new());
+        writer.write_all(json).unwrap();
+
+        assert!(writer.finish().is_err(), "{}", String::from_utf8_lossy(json));
+    }
+}
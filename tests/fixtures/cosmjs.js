@@ -0,0 +1,43 @@
+// Regenerates the expected output in cosmjs.json with the sign doc serialization of @cosmjs/amino
+// (https://github.com/cosmos/cosmjs/blob/main/packages/amino/src/signdoc.ts):
+//
+//	node cosmjs.js < cosmjs.json > cosmjs.json.new && mv cosmjs.json.new cosmjs.json
+"use strict";
+
+function sortedObject(obj) {
+    if (typeof obj !== "object" || obj === null) {
+        return obj;
+    }
+    if (Array.isArray(obj)) {
+        return obj.map(sortedObject);
+    }
+    const sortedKeys = Object.keys(obj).sort();
+    const result = {};
+    sortedKeys.forEach((key) => {
+        result[key] = sortedObject(obj[key]);
+    });
+    return result;
+}
+
+function sortedJsonStringify(obj) {
+    return JSON.stringify(sortedObject(obj));
+}
+
+function escapeCharacters(input) {
+    const amp = /&/g;
+    const lt = /</g;
+    const gt = />/g;
+    return input.replace(amp, "\\u0026").replace(lt, "\\u003c").replace(gt, "\\u003e");
+}
+
+function serializeSignDoc(signDoc) {
+    return escapeCharacters(sortedJsonStringify(signDoc));
+}
+
+const fixtures = JSON.parse(require("fs").readFileSync(0, "utf8"));
+
+for (const fixture of fixtures) {
+    fixture.output = serializeSignDoc(fixture.input);
+}
+
+process.stdout.write(JSON.stringify(fixtures, null, 2) + "\n");
@@ -0,0 +1,43 @@
+// Regenerates the expected output in go.json with encoding/json:
+//
+//	go run go.go < go.json > go.json.new && mv go.json.new go.json
+package main
+
+import (
+	"bytes"
+	"encoding/json"
+	"os"
+)
+
+type fixture struct {
+	Input        any    `json:"input"`
+	EscapeHTML   string `json:"escape_html"`
+	NoEscapeHTML string `json:"no_escape_html"`
+}
+
+func encode(value any, escapeHTML bool) string {
+	var buffer bytes.Buffer
+	encoder := json.NewEncoder(&buffer)
+	encoder.SetEscapeHTML(escapeHTML)
+	if err := encoder.Encode(value); err != nil {
+		panic(err)
+	}
+	return string(bytes.TrimSuffix(buffer.Bytes(), []byte("\n")))
+}
+
+func main() {
+	var fixtures []fixture
+	if err := json.NewDecoder(os.Stdin).Decode(&fixtures); err != nil {
+		panic(err)
+	}
+	for i := range fixtures {
+		fixtures[i].EscapeHTML = encode(fixtures[i].Input, true)
+		fixtures[i].NoEscapeHTML = encode(fixtures[i].Input, false)
+	}
+	output := json.NewEncoder(os.Stdout)
+	output.SetEscapeHTML(false)
+	output.SetIndent("", "  ")
+	if err := output.Encode(fixtures); err != nil {
+		panic(err)
+	}
+}
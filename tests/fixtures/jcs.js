@@ -0,0 +1,49 @@
+// Regenerates the expected output in jcs.json with the RFC 8785 reference implementation
+// (https://github.com/cyberphone/json-canonicalization), which relies on JSON.stringify:
+//
+//	node jcs.js < jcs.json > jcs.json.new && mv jcs.json.new jcs.json
+"use strict";
+
+function canonicalize(object) {
+    let buffer = "";
+    serialize(object);
+    return buffer;
+
+    function serialize(object) {
+        if (object === null || typeof object !== "object" || object.toJSON != null) {
+            buffer += JSON.stringify(object);
+        } else if (Array.isArray(object)) {
+            buffer += "[";
+            let next = false;
+            object.forEach((element) => {
+                if (next) {
+                    buffer += ",";
+                }
+                next = true;
+                serialize(element);
+            });
+            buffer += "]";
+        } else {
+            buffer += "{";
+            let next = false;
+            Object.keys(object).sort().forEach((property) => {
+                if (next) {
+                    buffer += ",";
+                }
+                next = true;
+                buffer += JSON.stringify(property);
+                buffer += ":";
+                serialize(object[property]);
+            });
+            buffer += "}";
+        }
+    }
+}
+
+const fixtures = JSON.parse(require("fs").readFileSync(0, "utf8"));
+
+for (const fixture of fixtures) {
+    fixture.output = canonicalize(fixture.input);
+}
+
+process.stdout.write(JSON.stringify(fixtures, null, 2) + "\n");
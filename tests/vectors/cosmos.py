@@ -0,0 +1,28 @@
+# Regenerates the public key and signature in src/cosmos/tests.rs with Python's `cryptography` package,
+# which signs with RFC 6979 deterministic ECDSA.
+#
+#     python3 tests/vectors/cosmos.py
+
+import base64
+import re
+
+from cryptography.hazmat.primitives import hashes
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+ORDER = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+
+# The sign bytes are read from the test, so the two cannot drift apart
+with open("src/cosmos/tests.rs", encoding="utf-8") as tests:
+    sign_bytes = re.search(r'const SIGN_BYTES: &str = r#"(.*)"#;', tests.read()).group(1).encode()
+
+key = ec.derive_private_key(int.from_bytes(bytes([1] * 32), "big"), ec.SECP256K1())
+public = key.public_key().public_bytes(Encoding.X962, PublicFormat.CompressedPoint)
+
+r, s = decode_dss_signature(key.sign(sign_bytes, ec.ECDSA(hashes.SHA256(), deterministic_signing=True)))
+# Cosmos only accepts S in the lower half of the group order
+s = min(s, ORDER - s)
+
+print("PUB_KEY  ", base64.b64encode(public).decode())
+print("SIGNATURE", base64.b64encode(r.to_bytes(32, "big") + s.to_bytes(32, "big")).decode())
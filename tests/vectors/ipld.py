@@ -0,0 +1,32 @@
+# Regenerates the expected CIDs in src/ipld/tests.rs with Python's hashlib and base64 modules.
+#
+#     python3 tests/vectors/ipld.py
+
+import base64
+import hashlib
+
+DAG_JSON = 0x0129
+SHA2_256 = 0x12
+
+
+def varint(value):
+    out = bytearray()
+    while value >= 0x80:
+        out.append(value & 0x7F | 0x80)
+        value >>= 7
+    out.append(value)
+    return bytes(out)
+
+
+def cid(block):
+    digest = hashlib.sha256(block).digest()
+    raw = varint(1) + varint(DAG_JSON) + varint(SHA2_256) + varint(len(digest)) + digest
+    return "b" + base64.b32encode(raw).decode().lower().rstrip("=")
+
+
+# DAG-JSON sorts keys by length, then bytes
+parent = cid(b'{"name":"parent"}')
+block = '{"b":1.5,"aa":{"/":"%s"},"ccc":{"/":{"bytes":"AAEC/w"}},"tab\\t":"line\\n\\u0001"}' % parent
+
+print("parent", parent)
+print("block ", cid(block.encode()))
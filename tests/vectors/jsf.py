@@ -0,0 +1,26 @@
+# Regenerates the expected signature in src/jsf/tests.rs with Python's `cryptography` package.
+#
+#     python3 tests/vectors/jsf.py
+
+import base64
+import json
+
+from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+
+def b64url(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+key = Ed25519PrivateKey.from_private_bytes(bytes([7] * 32))
+public = key.public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+
+signature = {"algorithm": "Ed25519", "publicKey": {"kty": "OKP", "crv": "Ed25519", "x": b64url(public)}}
+document = {"statement": "Hello", "count": 3, "signature": signature}
+
+# JSF signs the JCS form of the document, with the signature object but without its value. For this
+# ASCII-only document, sorted compact json.dumps is the JCS form.
+canonical = json.dumps(document, sort_keys=True, separators=(",", ":")).encode()
+print("x    ", b64url(public))
+print("value", b64url(key.sign(canonical)))
@@ -0,0 +1,30 @@
+# Regenerates the expected tokens in src/jws/tests.rs with Python's `cryptography` package.
+#
+#     python3 tests/vectors/jws.py
+
+import base64
+import json
+
+from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+
+
+def b64url(data):
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+def compact(value):
+    return json.dumps(value, sort_keys=True, separators=(",", ":")).encode()
+
+
+key = Ed25519PrivateKey.from_private_bytes(bytes([7] * 32))
+payload = compact({"b": [True, None], "a": 1})
+
+protected = b64url(compact({"alg": "EdDSA"}))
+encoded = b64url(payload)
+signature = key.sign(f"{protected}.{encoded}".encode())
+print("ATTACHED ", f"{protected}.{encoded}.{b64url(signature)}")
+
+# RFC 7797: the payload is signed as it is and left out of the token
+protected = b64url(compact({"alg": "EdDSA", "b64": False, "crit": ["b64"]}))
+signature = key.sign(protected.encode() + b"." + payload)
+print("UNENCODED", f"{protected}..{b64url(signature)}")